@@ -1,17 +1,20 @@
 pub const HELP_MSG: &str = "sudo - execute a command as another user
 
 usage: sudo -h | -K | -k | -V
-usage: sudo -v [-knS] [-g group] [-h host] [-u user]
-usage: sudo -l [-knS] [-g group] [-h host] [-U user] [-u user] [command]
-usage: sudo [-bEHknPS] [-D directory] [-g group] [-h host] [-R
-            directory] [-u user] [VAR=value] [-i|-s] [<command>]
-usage: sudo -e [-knS] [-D directory] [-g group] [-h host] [-R
-            directory] [-u user] file ...
+usage: sudo -v [-AknS] [-g group] [-h host] [-p prompt] [-u user]
+usage: sudo -l [-AknS] [-g group] [-h host] [-p prompt] [-U user] [-u user]
+            [command]
+usage: sudo [-AbEHknPS] [-D directory] [-g group] [-h host] [-p prompt]
+            [-R directory] [-u user] [VAR=value] [-i|-s] [<command>]
+usage: sudo -e [-AknS] [-D directory] [-g group] [-h host] [-p prompt]
+            [-R directory] [-u user] file ...
 
 Options:
+  -A, --askpass                 use a helper program for password prompting
   -b, --background              run command in the background
   -D, --chdir=directory         change the working directory before running command
-  -E, --preserve-env=list       preserve specific environment variables
+  -E, --preserve-env            preserve user environment when running command
+      --preserve-env=list       preserve specific environment variables
   -e, --edit                    edit files instead of running a command
   -g, --group=group             run command as the specified group name or ID
   -H, --set-home                set HOME variable to target user's home dir
@@ -23,8 +26,10 @@ Options:
   -k, --reset-timestamp         invalidate timestamp file
   -l, --list                    list user's privileges or check a specific command; use twice
                                 for longer format
-  -n, --non-interactive         non-interactive mode, no prompts are used
+  -n, --non-interactive         non-interactive mode, no prompts are used; exits with status 2
+                                if a password is required
   -P, --preserve-groups         preserve group vector instead of setting to target's
+  -p, --prompt=prompt           use the specified password prompt
   -R, --chroot=directory        change the root directory before running command
   -S, --stdin                   read password from standard input
   -s, --shell                   run shell as the target user; a command may also be specified
@@ -35,7 +40,7 @@ Options:
   --                            stop processing command line arguments";
 
 pub const USAGE_MSG: &str = "usage: sudo -h | -K | -k | -V
-usage: sudo -v [-knS] [-g group] [-h host] [-u user]
-usage: sudo -l [-knS] [-g group] [-h host] [-U user] [-u user] [command]
-usage: sudo [-bEHknPS] [-D directory] [-g group] [-h host] [-R directory] [-u user] [VAR=value] [-i|-s] [<command>]
-usage: sudo -e [-knS] [-D directory] [-g group] [-h host] [-R directory] [-u user] file ...";
+usage: sudo -v [-AknS] [-g group] [-h host] [-p prompt] [-u user]
+usage: sudo -l [-AknS] [-g group] [-h host] [-p prompt] [-U user] [-u user] [command]
+usage: sudo [-AbEHknPS] [-D directory] [-g group] [-h host] [-p prompt] [-R directory] [-u user] [VAR=value] [-i|-s] [<command>]
+usage: sudo -e [-AknS] [-D directory] [-g group] [-h host] [-p prompt] [-R directory] [-u user] file ...";
@@ -19,6 +19,7 @@ pub enum SudoAction {
 
 #[derive(Debug, Default, PartialEq, Clone)]
 pub struct SudoOptions {
+    pub askpass: bool,
     pub background: bool,
     pub chroot: Option<PathBuf>,
     pub directory: Option<PathBuf>,
@@ -28,11 +29,16 @@ pub struct SudoOptions {
     pub non_interactive: bool,
     pub other_user: Option<String>,
     pub preserve_env: Vec<String>,
+    // preserve the entire environment (-E)
+    pub short_preserve_env: bool,
     pub preserve_groups: bool,
+    pub prompt: Option<String>,
     pub set_home: bool,
     pub shell: bool,
     pub stdin: bool,
     pub user: Option<String>,
+    // list in the long format (-ll)
+    pub long_list: bool,
     // additional environment
     pub env_var_list: Vec<(String, String)>,
     // resulting action enum
@@ -57,16 +63,18 @@ enum SudoArg {
 }
 
 impl SudoOptions {
-    const TAKES_ARGUMENT_SHORT: &[char] = &['D', 'E', 'g', 'h', 'R', 'U', 'u'];
+    const TAKES_ARGUMENT_SHORT: &[char] = &['D', 'g', 'h', 'p', 'R', 'U', 'u'];
     const TAKES_ARGUMENT: &[&'static str] = &[
         "chdir",
-        "preserve-env",
         "group",
         "host",
         "chroot",
         "other-user",
+        "prompt",
         "user",
     ];
+    // these only take an argument in the assignment syntax
+    const TAKES_OPTIONAL_ARGUMENT: &[&'static str] = &["preserve-env"];
 
     /// argument assignments and shorthand options preprocessing
     fn normalize_arguments<I>(iter: I) -> Result<Vec<SudoArg>, String>
@@ -88,7 +96,9 @@ impl SudoOptions {
                         // convert assignment to normal tokens
                         let (key, value) = long_arg.split_once('=').unwrap();
                         // only accept arguments when one is expected
-                        if !Self::TAKES_ARGUMENT.contains(&&key[2..]) {
+                        if !Self::TAKES_ARGUMENT.contains(&&key[2..])
+                            && !Self::TAKES_OPTIONAL_ARGUMENT.contains(&&key[2..])
+                        {
                             Err(format!("'{}' does not take any arguments", key))?;
                         }
                         processed.push(SudoArg::Argument(key.to_string(), value.to_string()));
@@ -201,6 +211,7 @@ impl SudoOptions {
                 || self.login
                 || self.shell
                 || !self.preserve_env.is_empty()
+                || self.short_preserve_env
                 || self.other_user.is_some()
                 || self.directory.is_some()
                 || self.chroot.is_some())
@@ -216,6 +227,7 @@ impl SudoOptions {
                 || self.login
                 || self.shell
                 || !self.preserve_env.is_empty()
+                || self.short_preserve_env
                 || self.directory.is_some()
                 || self.chroot.is_some())
         {
@@ -230,7 +242,8 @@ impl SudoOptions {
                 || self.login
                 || self.shell
                 || self.other_user.is_some()
-                || !self.preserve_env.is_empty())
+                || !self.preserve_env.is_empty()
+                || self.short_preserve_env)
         {
             Err("invalid argument found for '--edit'")?;
         }
@@ -252,9 +265,15 @@ impl SudoOptions {
         for arg in arg_iter {
             match arg {
                 SudoArg::Flag(flag) => match flag.as_str() {
+                    "-A" | "--askpass" => {
+                        options.askpass = true;
+                    }
                     "-b" | "--background" => {
                         options.background = true;
                     }
+                    "-E" | "--preserve-env" => {
+                        options.short_preserve_env = true;
+                    }
                     "-e" | "--edit" => {
                         options.edit = true;
                     }
@@ -274,6 +293,8 @@ impl SudoOptions {
                         options.reset_timestamp = true;
                     }
                     "-l" | "--list" => {
+                        // specifying this option twice selects the long format
+                        options.long_list = options.list;
                         options.list = true;
                     }
                     "-n" | "--non-interactive" => {
@@ -302,15 +323,18 @@ impl SudoOptions {
                     "-D" | "--chdir" => {
                         options.directory = Some(PathBuf::from(value));
                     }
-                    "-E" | "--preserve-env" => {
-                        options.preserve_env = value.split(',').map(str::to_string).collect()
-                    }
+                    "--preserve-env" => options
+                        .preserve_env
+                        .extend(value.split(',').map(str::to_string)),
                     "-g" | "--group" => {
                         options.group = Some(value);
                     }
                     "-h" | "--host" => {
                         options.host = Some(value);
                     }
+                    "-p" | "--prompt" => {
+                        options.prompt = Some(value);
+                    }
                     "-R" | "--chroot" => {
                         options.chroot = Some(PathBuf::from(value));
                     }
@@ -1,4 +1,4 @@
-use std::path::{Path, PathBuf};
+use std::path::{Component, Path, PathBuf};
 
 use super::{resolve::resolve_path, Error};
 
@@ -60,6 +60,44 @@ impl CommandAndArguments {
 
         Ok(CommandAndArguments { command, arguments })
     }
+
+    /// The pseudo-command that is checked against the policy for `sudoedit`; the files to edit are
+    /// its arguments, made absolute so they can be matched against the paths in the policy
+    pub fn try_from_edit(files: Vec<PathBuf>) -> Result<Self, Error> {
+        let mut arguments = Vec::with_capacity(files.len());
+        for file in files {
+            let Some(name) = file.file_name() else {
+                return Err(Error::InvalidCommand(file));
+            };
+            // ".." in the directory part should not trick the policy; symbolic links are not
+            // resolved here, since editing files below a link is refused altogether
+            let mut path = if file.is_absolute() {
+                PathBuf::new()
+            } else {
+                std::env::current_dir()?
+            };
+            for component in file.parent().into_iter().flat_map(Path::components) {
+                match component {
+                    Component::ParentDir => {
+                        path.pop();
+                    }
+                    Component::CurDir => {}
+                    component => path.push(component),
+                }
+            }
+            std::fs::metadata(&path)?;
+            path.push(name);
+            let Some(path) = path.to_str() else {
+                return Err(Error::InvalidCommand(path));
+            };
+            arguments.push(path.to_string());
+        }
+
+        Ok(CommandAndArguments {
+            command: "sudoedit".into(),
+            arguments,
+        })
+    }
 }
 
 #[cfg(test)]
@@ -119,6 +157,21 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_build_edit_command() {
+        assert_eq!(
+            CommandAndArguments::try_from_edit(vec!["/etc/hosts".into(), "/etc/../tmp/x".into()])
+                .unwrap(),
+            CommandAndArguments {
+                command: "sudoedit".into(),
+                arguments: vec!["/etc/hosts".into(), "/tmp/x".into()]
+            }
+        );
+
+        assert!(CommandAndArguments::try_from_edit(vec!["/etc/..".into()]).is_err());
+        assert!(CommandAndArguments::try_from_edit(vec!["/nonexistent/dir/file".into()]).is_err());
+    }
+
     #[test]
     fn qualified_paths() {
         use super::is_qualified;
@@ -1,10 +1,14 @@
 use crate::cli::{SudoAction, SudoOptions};
 use crate::system::{hostname, Group, Process, User};
+use std::fs::File;
 use std::path::PathBuf;
 
 use super::{
     command::CommandAndArguments,
-    resolve::{resolve_current_user, resolve_launch_and_shell, resolve_target_user_and_group},
+    resolve::{
+        resolve_current_user, resolve_launch_and_shell, resolve_other_user,
+        resolve_target_user_and_group,
+    },
     Error,
 };
 
@@ -12,14 +16,21 @@ use super::{
 pub struct Context {
     // cli options
     pub preserve_env: Vec<String>,
+    pub short_preserve_env: bool,
+    pub env_var_list: Vec<(String, String)>,
     pub set_home: bool,
     pub launch: LaunchType,
     pub chdir: Option<PathBuf>,
     pub command: CommandAndArguments,
+    pub command_file: Option<File>,
     pub target_user: User,
     pub target_group: Group,
     pub stdin: bool,
+    pub non_interactive: bool,
+    // the password prompt from -p or SUDO_PROMPT
+    pub prompt: Option<String>,
     pub use_session_records: bool,
+    pub other_user: Option<User>,
     // system
     pub hostname: String,
     pub path: String,
@@ -35,16 +46,30 @@ pub enum LaunchType {
 }
 
 impl Context {
-    pub fn build_from_options(sudo_options: SudoOptions, path: String) -> Result<Context, Error> {
-        let hostname = hostname();
+    /// Build the context for a request; `path` determines the search path for the command, given
+    /// the invoking user and the host that the policy is evaluated for.
+    pub fn build_from_options(
+        sudo_options: SudoOptions,
+        path: impl FnOnce(&User, &str) -> String,
+    ) -> Result<Context, Error> {
+        // the policy can only be evaluated for another host when listing privileges
+        let hostname = match (&sudo_options.action, &sudo_options.host) {
+            (SudoAction::List(_), Some(host)) => host.clone(),
+            _ => hostname(),
+        };
         let current_user = resolve_current_user()?;
+        let path = path(&current_user, &hostname);
+        let other_user = resolve_other_user(&sudo_options.other_user)?;
         let (target_user, target_group) =
             resolve_target_user_and_group(&sudo_options.user, &sudo_options.group, &current_user)?;
         let (launch, shell) = resolve_launch_and_shell(&sudo_options, &current_user, &target_user);
         let command = match sudo_options.action {
+            // `sudo -l` without a command lists all permissions instead of checking a specific one
+            SudoAction::List(args) if args.is_empty() => Default::default(),
             SudoAction::Run(args) | SudoAction::List(args) => {
                 CommandAndArguments::try_from_args(shell, args, &path)?
             }
+            SudoAction::Edit(files) => CommandAndArguments::try_from_edit(files)?,
             _ => Default::default(),
         };
 
@@ -52,18 +77,58 @@ impl Context {
             hostname,
             path,
             command,
+            command_file: None,
             current_user,
             target_user,
             target_group,
             set_home: sudo_options.set_home,
             preserve_env: sudo_options.preserve_env,
+            short_preserve_env: sudo_options.short_preserve_env,
+            env_var_list: sudo_options.env_var_list,
             use_session_records: !sudo_options.reset_timestamp,
+            other_user,
             launch,
             chdir: sudo_options.directory,
             stdin: sudo_options.stdin,
+            non_interactive: sudo_options.non_interactive,
+            prompt: sudo_options
+                .prompt
+                .or_else(|| std::env::var("SUDO_PROMPT").ok()),
             process: Process::new(),
         })
     }
+
+    /// Expand the escape sequences in a password prompt: `%u` is the invoking user, `%U` the
+    /// target user, `%h` and `%H` the short and full hostname, and `%p` the user whose password
+    /// is requested. `%%` is a literal percent sign, other sequences are left as they are.
+    pub fn expand_prompt(&self, prompt: &str, auth_user: &str) -> String {
+        let mut result = String::with_capacity(prompt.len());
+        let mut chars = prompt.chars();
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                result.push(c);
+                continue;
+            }
+            match chars.next() {
+                Some('u') => result.push_str(&self.current_user.name),
+                Some('U') => result.push_str(&self.target_user.name),
+                Some('h') => result.push_str(
+                    self.hostname
+                        .split_once('.')
+                        .map_or(&*self.hostname, |(short, _)| short),
+                ),
+                Some('H') => result.push_str(&self.hostname),
+                Some('p') => result.push_str(auth_user),
+                Some('%') => result.push('%'),
+                Some(other) => {
+                    result.push('%');
+                    result.push(other);
+                }
+                None => result.push('%'),
+            }
+        }
+        result
+    }
 }
 
 #[cfg(test)]
@@ -77,7 +142,7 @@ mod tests {
     fn test_build_context() {
         let options = SudoOptions::try_parse_from(["sudo", "echo", "hello"]).unwrap();
         let path = "/usr/local/sbin:/usr/local/bin:/usr/sbin:/usr/bin:/sbin:/bin";
-        let context = Context::build_from_options(options, path.to_string()).unwrap();
+        let context = Context::build_from_options(options, |_, _| path.to_string()).unwrap();
 
         let mut target_environment = HashMap::new();
         target_environment.insert("SUDO_USER".to_string(), context.current_user.name.clone());
@@ -87,4 +152,22 @@ mod tests {
         assert_eq!(context.hostname, hostname());
         assert_eq!(context.target_user.uid, 0);
     }
+
+    #[test]
+    fn test_expand_prompt() {
+        let options = SudoOptions::try_parse_from(["sudo", "true"]).unwrap();
+        let path = "/usr/bin:/bin";
+        let mut context = Context::build_from_options(options, |_, _| path.to_string()).unwrap();
+        context.hostname = "server.example.com".to_string();
+        let user = &context.current_user.name;
+
+        assert_eq!(
+            context.expand_prompt("[%u -> %U@%h] password for %p: ", "ferris"),
+            format!("[{user} -> root@server] password for ferris: ")
+        );
+        assert_eq!(
+            context.expand_prompt("%H: 100%% %x%", "ferris"),
+            "server.example.com: 100% %x%"
+        );
+    }
 }
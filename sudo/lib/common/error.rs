@@ -12,6 +12,9 @@ pub enum Error {
     Pam(PamError),
     IoError(std::io::Error),
     MaxAuthAttempts(usize),
+    InteractionRequired,
+    EnvironmentVar(Vec<String>),
+    PreserveEnvironment,
 }
 
 impl fmt::Display for Error {
@@ -28,6 +31,15 @@ impl fmt::Display for Error {
             Error::MaxAuthAttempts(num) => {
                 write!(f, "Maximum {num} incorrect authentication attempts")
             }
+            Error::InteractionRequired => write!(f, "a password is required"),
+            Error::EnvironmentVar(vars) => write!(
+                f,
+                "sorry, you are not allowed to set the following environment variables: {}",
+                vars.join(", ")
+            ),
+            Error::PreserveEnvironment => {
+                write!(f, "sorry, you are not allowed to preserve the environment")
+            }
         }
     }
 }
@@ -52,4 +64,26 @@ impl Error {
     pub fn conf(message: &str) -> Self {
         Self::Configuration(message.to_string())
     }
+
+    /// The exit status of sudo when it fails with this error: 2 if a password is required but
+    /// `-n` forbids asking for it, so that scripts can tell this case apart, and 1 otherwise.
+    /// This intentionally differs from the original sudo, which exits with 1 in both cases.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::InteractionRequired => 2,
+            _ => 1,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Error;
+
+    #[test]
+    fn exit_codes() {
+        assert_eq!(Error::InteractionRequired.exit_code(), 2);
+        assert_eq!(Error::MaxAuthAttempts(3).exit_code(), 1);
+        assert_eq!(Error::auth("no").exit_code(), 1);
+    }
 }
@@ -113,6 +113,22 @@ pub(super) fn resolve_target_user_and_group(
     }
 }
 
+/// Resolve the user whose privileges should be listed with `sudo -U`
+pub(super) fn resolve_other_user(name_or_id: &Option<String>) -> Result<Option<User>, Error> {
+    let Some(name_or_id) = name_or_id else {
+        return Ok(None);
+    };
+
+    let user = match NameOrId::parse(name_or_id) {
+        Some(NameOrId::Name(name)) => User::from_name(name)?,
+        Some(NameOrId::Id(uid)) => User::from_uid(uid)?,
+        None => None,
+    };
+
+    user.map(Some)
+        .ok_or_else(|| Error::UserNotFound(name_or_id.clone()))
+}
+
 /// Check whether a path points to a regular file and any executable flag is set
 fn is_valid_executable(path: &PathBuf) -> bool {
     if path.is_file() {
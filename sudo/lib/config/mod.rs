@@ -0,0 +1,170 @@
+#![forbid(unsafe_code)]
+
+//! Global configuration that is not part of the sudoers policy, such as the location of the
+//! sudoers file. Defaults are chosen at build time and can be overridden in `sudo.conf`.
+//!
+//! The following environment variables are read at build time:
+//! - `SUDO_RS_SUDOERS_PATH`: the default location of the sudoers file
+//! - `SUDO_RS_SUDO_CONF_PATH`: the location of `sudo.conf`
+
+use std::io::{self, ErrorKind, Read};
+use std::path::{Path, PathBuf};
+
+use crate::system::secure_open;
+
+/// Location of the sudoers file, unless overridden in `sudo.conf`
+pub const DEFAULT_SUDOERS_PATH: &str = match option_env!("SUDO_RS_SUDOERS_PATH") {
+    Some(path) => path,
+    None => "/etc/sudoers.test",
+};
+
+/// Location of the `sudo.conf` file
+pub const SUDO_CONF_PATH: &str = match option_env!("SUDO_RS_SUDO_CONF_PATH") {
+    Some(path) => path,
+    None => "/etc/sudo.conf",
+};
+
+#[derive(Debug, PartialEq)]
+pub struct SudoConf {
+    sudoers_path: PathBuf,
+    askpass_path: Option<PathBuf>,
+}
+
+impl Default for SudoConf {
+    fn default() -> Self {
+        SudoConf {
+            sudoers_path: DEFAULT_SUDOERS_PATH.into(),
+            askpass_path: None,
+        }
+    }
+}
+
+impl SudoConf {
+    /// Read the configuration from [SUDO_CONF_PATH]; a missing file means that all the defaults
+    /// are used. Like the sudoers file, `sudo.conf` must be owned by root and not be writable
+    /// by others.
+    pub fn load() -> io::Result<SudoConf> {
+        Self::load_from(Path::new(SUDO_CONF_PATH))
+    }
+
+    fn load_from(path: &Path) -> io::Result<SudoConf> {
+        let mut text = String::new();
+        match secure_open(path) {
+            Ok(mut file) => file.read_to_string(&mut text)?,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(Default::default()),
+            Err(err) => return Err(err),
+        };
+
+        Ok(Self::parse(&text))
+    }
+
+    /// Parse the contents of a `sudo.conf` file. Only the settings that apply to sudo-rs are
+    /// used; other lines are ignored, so that the file can be shared with the original sudo.
+    ///
+    /// The location of the sudoers file is taken from the `sudoers_file` argument of the
+    /// sudoers policy plugin:
+    /// ```text
+    /// Plugin sudoers_policy sudoers.so sudoers_file=/etc/sudoers
+    /// ```
+    ///
+    /// The helper program for `sudo -A` is taken from a `Path` line:
+    /// ```text
+    /// Path askpass /usr/bin/ssh-askpass
+    /// ```
+    fn parse(text: &str) -> SudoConf {
+        let mut conf = SudoConf::default();
+
+        for line in text.lines() {
+            let line = line.split_once('#').map_or(line, |(line, _comment)| line);
+            let mut words = line.split_whitespace();
+            match (words.next(), words.next()) {
+                (Some("Plugin"), Some("sudoers_policy")) => {
+                    // skip the path of the plugin
+                    for arg in words.skip(1) {
+                        if let Some(path) = arg.strip_prefix("sudoers_file=") {
+                            conf.sudoers_path = path.into();
+                        }
+                    }
+                }
+                (Some("Path"), Some("askpass")) => {
+                    conf.askpass_path = words.next().map(PathBuf::from);
+                }
+                _ => {}
+            }
+        }
+
+        conf
+    }
+
+    /// A configuration that uses `path` as the location of the sudoers file; this override only
+    /// exists in test builds
+    #[cfg(test)]
+    pub(crate) fn with_sudoers_path(path: impl Into<PathBuf>) -> SudoConf {
+        SudoConf {
+            sudoers_path: path.into(),
+            ..Default::default()
+        }
+    }
+
+    /// The location of the sudoers file
+    pub fn sudoers_path(&self) -> PathBuf {
+        self.sudoers_path.clone()
+    }
+
+    /// The helper program that asks for a password when `sudo -A` is used
+    pub fn askpass_path(&self) -> Option<&Path> {
+        self.askpass_path.as_deref()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn empty_conf_uses_defaults() {
+        assert_eq!(SudoConf::parse(""), SudoConf::default());
+        assert_eq!(
+            SudoConf::parse("# Plugin sudoers_policy sudoers.so sudoers_file=/etc/foo\n"),
+            SudoConf::default()
+        );
+    }
+
+    #[test]
+    fn sudoers_file_from_plugin_line() {
+        let conf = SudoConf::parse(
+            "Path askpass /usr/bin/ssh-askpass\n\
+             Plugin sudoers_policy sudoers.so sudoers_file=/etc/sudoers sudoers_mode=0440\n\
+             Plugin sudoers_io sudoers.so sudoers_file=/etc/bogus\n",
+        );
+        assert_eq!(conf.sudoers_path, Path::new("/etc/sudoers"));
+    }
+
+    #[test]
+    fn askpass_from_path_line() {
+        let conf = SudoConf::parse("Path noexec /usr/libexec/sudo_noexec.so\n");
+        assert_eq!(conf.askpass_path(), None);
+
+        let conf = SudoConf::parse("Path askpass /usr/bin/ssh-askpass # for -A\n");
+        assert_eq!(conf.askpass_path(), Some(Path::new("/usr/bin/ssh-askpass")));
+    }
+
+    #[test]
+    fn sudoers_path_override() {
+        use std::io::Write;
+
+        let mut sudoers = tempfile::NamedTempFile::new().unwrap();
+        writeln!(sudoers, "root ALL=(ALL:ALL) ALL").unwrap();
+
+        let conf = SudoConf::with_sudoers_path(sudoers.path());
+        assert_eq!(conf.sudoers_path(), sudoers.path());
+        let (_, errors) = crate::sudoers::Sudoers::new(conf.sudoers_path()).unwrap();
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn missing_conf_uses_defaults() {
+        let conf = SudoConf::load_from(Path::new("/nonexistent/sudo.conf")).unwrap();
+        assert_eq!(conf, SudoConf::default());
+    }
+}
@@ -25,19 +25,35 @@ use settings_dsl::*;
 defaults! {
     always_query_group_plugin = false
     always_set_home           = false
+    env_editor                = true
     env_reset                 = true
     mail_badpass              = true
     match_group_by_gid        = false
+    passprompt_override       = false
+    rootpw                    = false
+    runaspw                   = false
+    set_home                  = false
+    set_logname               = true
+    setenv                    = false
+    targetpw                  = false
     use_pty                   = false
     visiblepw                 = false
 
+    passwd_timeout            = 300 (!= 0)         {fractional_minutes}
     passwd_tries              = 3
+    timestamp_timeout         = 900 (!= 0)         {fractional_minutes}
     umask                     = 0o22 (!= 0o777)    [0..=0o777; radix: 8]
 
     editor                    = "/usr/bin/editor"
+    env_file                  = None (!= None)
+    group_plugin              = None (!= None)
     lecture_file              = None
     lecture                   = "once" (!= "never") [once, always, never]
+    listpw                    = "any" (!= "never") [all, always, any, never]
+    passprompt                = None (!= None)
+    restricted_env_file       = None (!= None)
     secure_path               = None (!= None)
+    timestamp_type            = "tty" [global, ppid, tty]
     verifypw                  = "all" (!= "never") [all, always, any, never]
 
     env_keep                  = ["COLORS", "DISPLAY", "HOSTNAME", "KRB5CCNAME", "LS_COLORS", "PATH",
@@ -54,6 +70,17 @@ defaults! {
                                 "PYTHONINSPECT", "PYTHONUSERBASE", "RUBYLIB", "RUBYOPT", "*=()*"]
 }
 
+/// Parse a number of minutes, which may have a fractional part, into a number of seconds; this
+/// is how sudo specifies timeouts
+fn fractional_minutes(text: &str) -> Option<i128> {
+    let minutes = text
+        .parse::<f64>()
+        .ok()
+        .filter(|minutes| minutes.is_finite())?;
+
+    Some((minutes * 60.0).round() as i128)
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -78,22 +105,46 @@ mod test {
 
         test! { always_query_group_plugin => Flag(false) };
         test! { always_set_home => Flag(false) };
+        test! { env_editor => Flag(true) };
         test! { env_reset => Flag(true) };
         test! { mail_badpass => Flag(true) };
         test! { match_group_by_gid => Flag(false) };
+        test! { passprompt_override => Flag(false) };
+        test! { rootpw => Flag(false) };
+        test! { runaspw => Flag(false) };
+        test! { set_home => Flag(false) };
+        test! { set_logname => Flag(true) };
+        test! { setenv => Flag(false) };
+        test! { targetpw => Flag(false) };
         test! { use_pty => Flag(false) };
         test! { visiblepw => Flag(false) };
+        test! { passwd_timeout => Integer(OptTuple { default: 300, negated: Some(0) }, _) };
         test! { passwd_tries => Integer(OptTuple { default: 3, negated: None }, _) };
+        test! { timestamp_timeout => Integer(OptTuple { default: 900, negated: Some(0) }, _) };
         test! { umask => Integer(OptTuple { default: 18, negated: Some(511) }, _) };
         test! { editor => Text(OptTuple { default: Some("/usr/bin/editor"), negated: None }) };
+        test! { env_file => Text(OptTuple { default: None, negated: Some(None) }) };
+        test! { group_plugin => Text(OptTuple { default: None, negated: Some(None) }) };
         test! { lecture_file => Text(_) };
         test! { lecture => Enum(OptTuple { default: StrEnum { value: "once", possible_values: [_, "always", _] }, negated: Some(StrEnum { value: "never", .. }) }) };
+        test! { listpw => Enum(OptTuple { default: StrEnum { value: "any", possible_values: [_, "always", _, _] }, negated: Some(StrEnum { value: "never", .. }) }) };
+        test! { passprompt => Text(OptTuple { default: None, negated: Some(None) }) };
+        test! { restricted_env_file => Text(OptTuple { default: None, negated: Some(None) }) };
         test! { secure_path => Text(OptTuple { default: None, negated: Some(None) }) };
         test! { env_keep => List(_) };
         test! { env_check => List(["COLORTERM", "LANG", "LANGUAGE", "LC_*", "LINGUAS", "TERM", "TZ"]) };
         test! { env_delete => List(_) };
+        test! { timestamp_type => Enum(OptTuple { default: StrEnum { value: "tty", possible_values: ["global", "ppid", "tty"] }, negated: None }) };
         test! { verifypw => Enum(OptTuple { default: StrEnum { value: "all", possible_values: [_, "always", "any", _] }, negated: Some(StrEnum { value: "never", .. }) }) };
 
+        let Some(Integer(_, checker)) = sudo_default("timestamp_timeout") else { unreachable!() };
+        assert_eq!(checker("15"), Some(900));
+        assert_eq!(checker("0.1"), Some(6));
+        assert_eq!(checker("-1"), Some(-60));
+        assert_eq!(checker("1.2.3"), None);
+        let Some(Integer(_, checker)) = sudo_default("passwd_tries") else { unreachable!() };
+        assert_eq!(checker("-1"), None);
+
         let myenum = StrEnum::new("hello", &["hello", "goodbye"]).unwrap();
         assert!(&myenum as &str == "hello");
     }
@@ -3,7 +3,8 @@ use std::io::{BufRead, BufReader};
 use std::ops::Range;
 use std::path::Path;
 
-pub(crate) fn cited_error(message: &str, range: Range<(usize, usize)>, path: impl AsRef<Path>) {
+/// Report an error at a position in a file, citing the offending line
+pub fn cited_error(message: &str, range: Range<(usize, usize)>, path: impl AsRef<Path>) {
     let path_str = path.as_ref().display();
     let Range {
         start: (line, col),
@@ -37,6 +38,7 @@ pub(crate) fn cited_error(message: &str, range: Range<(usize, usize)>, path: imp
     let _ = citation();
 }
 
+#[macro_export]
 macro_rules! diagnostic {
     ($str:expr, $path:tt @ $pos:ident) => {
         if let Some(range) = $pos {
@@ -50,4 +52,4 @@ macro_rules! diagnostic {
     };
 }
 
-pub(crate) use diagnostic;
+pub use diagnostic;
@@ -0,0 +1,259 @@
+#![forbid(unsafe_code)]
+
+//! Implementation of `sudoedit`: the files are copied to temporary files owned by the invoking
+//! user, which are edited using the editor of that user; afterwards the changes are written back
+//! with the permissions of the target user.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, ErrorKind, Read, Write};
+use std::os::unix::fs::{MetadataExt, OpenOptionsExt};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::common::{Context, Error};
+use crate::log::user_info;
+use crate::system::{file::file_error, set_target_user, with_effective_user, Group, User};
+
+/// Directory in which the temporary copies are created
+const TEMP_DIR: &str = "/var/tmp";
+
+struct EditFile {
+    target: PathBuf,
+    temp: PathBuf,
+    original: Vec<u8>,
+}
+
+/// Edit the files that are the arguments of `context.command`; `editor` is the value of the
+/// `editor` setting, a colon-separated list of editors that is used when the user did not set
+/// one of `SUDO_EDITOR`, `VISUAL` or `EDITOR`, or when `env_editor` is disabled.
+pub fn edit_files(context: &Context, editor: &str, env_editor: bool) -> Result<(), Error> {
+    let user = &context.current_user;
+    let Some(editor) = resolve_editor(editor, env_editor) else {
+        return Err(Error::conf("no usable editor found"));
+    };
+
+    let mut files = Vec::new();
+    for target in context.command.arguments.iter().map(PathBuf::from) {
+        check_directories(&target, user)?;
+
+        let original = with_effective_user(&context.target_user, context.target_group.gid, || {
+            match read_file(&target) {
+                // editing a file that does not exist yet creates it
+                Err(err) if err.kind() == ErrorKind::NotFound => Ok(Vec::new()),
+                result => result,
+            }
+        })?
+        .map_err(|err| file_error(&target, err))?;
+
+        let temp = with_effective_user(user, user.gid, || create_temp_file(&target, &original))?
+            .map_err(|err| file_error(&target, err))?;
+
+        files.push(EditFile {
+            target,
+            temp,
+            original,
+        });
+    }
+
+    let result = run_editor(&editor, &files, user).and_then(|()| {
+        for file in &files {
+            write_back(file, context)?;
+        }
+        Ok(())
+    });
+
+    for file in &files {
+        let _ = fs::remove_file(&file.temp);
+    }
+
+    result
+}
+
+/// Determine the editor (and its arguments) that should be used: if `env_editor` is set, the
+/// first of `SUDO_EDITOR`, `VISUAL` or `EDITOR` that is set, otherwise the first existing path in
+/// the colon-separated `editor` list
+pub fn resolve_editor(editor: &str, env_editor: bool) -> Option<Vec<String>> {
+    if env_editor {
+        for var in ["SUDO_EDITOR", "VISUAL", "EDITOR"] {
+            if let Ok(value) = std::env::var(var) {
+                let words = value
+                    .split_whitespace()
+                    .map(str::to_string)
+                    .collect::<Vec<_>>();
+                if !words.is_empty() {
+                    return Some(words);
+                }
+            }
+        }
+    }
+
+    editor
+        .split(':')
+        .find(|path| Path::new(path).is_file())
+        .map(|path| vec![path.to_string()])
+}
+
+/// Refuse to edit files in a directory that the invoking user can write to, or below a symbolic
+/// link to a directory, since the user could then replace the file by a link while it is being
+/// edited
+fn check_directories(target: &Path, user: &User) -> Result<(), Error> {
+    let Some(dir) = target.parent() else {
+        return Err(Error::InvalidCommand(target.to_path_buf()));
+    };
+    let dir = std::env::current_dir()
+        .map_err(|err| file_error(target, err))?
+        .join(dir);
+    let refuse = |reason: &str| -> Error {
+        file_error(target, io::Error::new(ErrorKind::PermissionDenied, reason)).into()
+    };
+
+    // walk the path one component at a time, so that no link is followed along the way
+    let mut path = PathBuf::new();
+    let mut meta = None;
+    for component in dir.components() {
+        path.push(component);
+        let current = fs::symlink_metadata(&path).map_err(|err| file_error(target, err))?;
+        if current.file_type().is_symlink() {
+            return Err(refuse(
+                "editing files in a path containing a symbolic link is not permitted",
+            ));
+        }
+        meta = Some(current);
+    }
+
+    match meta {
+        Some(meta) if user.uid != 0 && is_writable_by(&meta, user) => Err(refuse(
+            "editing files in a writable directory is not permitted",
+        )),
+        _ => Ok(()),
+    }
+}
+
+fn is_writable_by(meta: &fs::Metadata, user: &User) -> bool {
+    let mode = meta.mode();
+    (meta.uid() == user.uid && mode & 0o200 != 0)
+        || ((meta.gid() == user.gid || user.groups.contains(&meta.gid())) && mode & 0o020 != 0)
+        || mode & 0o002 != 0
+}
+
+fn open_no_follow(path: &Path, options: &mut OpenOptions) -> io::Result<File> {
+    let file = options
+        .custom_flags(libc::O_NOFOLLOW)
+        .open(path)
+        .map_err(|err| {
+            if err.raw_os_error() == Some(libc::ELOOP) {
+                io::Error::new(
+                    ErrorKind::PermissionDenied,
+                    "editing symbolic links is not permitted",
+                )
+            } else {
+                err
+            }
+        })?;
+
+    if file.metadata()?.is_file() {
+        Ok(file)
+    } else {
+        Err(io::Error::new(
+            ErrorKind::InvalidInput,
+            "not a regular file",
+        ))
+    }
+}
+
+fn read_file(path: &Path) -> io::Result<Vec<u8>> {
+    let mut contents = Vec::new();
+    open_no_follow(path, OpenOptions::new().read(true))?.read_to_end(&mut contents)?;
+
+    Ok(contents)
+}
+
+/// Create a new file in [TEMP_DIR] with the given contents; the extension of `target` is
+/// preserved so that editors can still recognize the type of file
+fn create_temp_file(target: &Path, contents: &[u8]) -> io::Result<PathBuf> {
+    let stem = target
+        .file_stem()
+        .map(|s| s.to_string_lossy())
+        .unwrap_or_default();
+    let extension = target
+        .extension()
+        .map(|s| format!(".{}", s.to_string_lossy()))
+        .unwrap_or_default();
+
+    let seed = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |time| time.subsec_nanos()) as u64
+        ^ u64::from(std::process::id()) << 32;
+
+    for attempt in 0..100u64 {
+        let unique = seed.wrapping_add(attempt.wrapping_mul(0x9e37_79b9_7f4a_7c15));
+        let path = Path::new(TEMP_DIR).join(format!("{stem}.{unique:016x}{extension}"));
+
+        // 'create_new' guarantees that we are not following a link that was placed there
+        match OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(&path)
+        {
+            Ok(mut file) => {
+                file.write_all(contents)?;
+                return Ok(path);
+            }
+            Err(err) if err.kind() == ErrorKind::AlreadyExists => continue,
+            Err(err) => return Err(err),
+        }
+    }
+
+    Err(io::Error::new(
+        ErrorKind::AlreadyExists,
+        "could not create a temporary file",
+    ))
+}
+
+fn run_editor(editor: &[String], files: &[EditFile], user: &User) -> Result<(), Error> {
+    let group =
+        Group::from_gid(user.gid)?.ok_or_else(|| Error::GroupNotFound(user.gid.to_string()))?;
+
+    let mut command = Command::new(&editor[0]);
+    command
+        .args(&editor[1..])
+        .args(files.iter().map(|file| &file.temp));
+    set_target_user(&mut command, user.clone(), group);
+
+    let status = command.status()?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(Error::IoError(io::Error::other(format!(
+            "{} exited with {status}, files left unchanged",
+            editor[0]
+        ))))
+    }
+}
+
+fn write_back(file: &EditFile, context: &Context) -> Result<(), Error> {
+    let user = &context.current_user;
+
+    // the temporary file is only read with the permissions of the invoking user
+    let contents = with_effective_user(user, user.gid, || read_file(&file.temp))?
+        .map_err(|err| file_error(&file.temp, err))?;
+
+    if contents == file.original {
+        user_info!("{} unchanged", file.target.display());
+        return Ok(());
+    }
+
+    with_effective_user(&context.target_user, context.target_group.gid, || {
+        let mut target = open_no_follow(
+            &file.target,
+            OpenOptions::new()
+                .write(true)
+                .create(true)
+                .truncate(true)
+                .mode(0o644),
+        )?;
+        target.write_all(&contents)
+    })?
+    .map_err(|err| file_error(&file.target, err).into())
+}
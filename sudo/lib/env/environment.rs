@@ -1,12 +1,13 @@
 use std::{
     collections::{hash_map::Entry, HashSet},
     ffi::{OsStr, OsString},
+    io::{ErrorKind, Read},
     os::unix::prelude::OsStrExt,
 };
 
-use crate::common::{CommandAndArguments, Context, Environment};
+use crate::common::{context::LaunchType, CommandAndArguments, Context, Environment, Error};
 use crate::sudoers::Policy;
-use crate::system::PATH_MAX;
+use crate::system::{secure_open, PATH_MAX};
 
 use super::wildcard_match::wildcard_match;
 
@@ -37,10 +38,12 @@ fn format_command(command_and_arguments: &CommandAndArguments) -> OsString {
     formatted
 }
 
-/// Construct sudo-specific environment variables
+/// Construct sudo-specific environment variables; `reset` indicates whether the target
+/// environment was built from scratch, or inherited from the invoking user
 fn add_extra_env(
     context: &Context,
     cfg: &impl Policy,
+    reset: bool,
     sudo_ps1: Option<OsString>,
     environment: &mut Environment,
 ) {
@@ -56,26 +59,47 @@ fn add_extra_env(
     );
     environment.insert("SUDO_USER".into(), context.current_user.name.clone().into());
     // target user
-    if let Entry::Vacant(entry) = environment.entry("MAIL".into()) {
-        entry.insert(format!("{PATH_MAILDIR}/{}", context.target_user.name).into());
+    let login = context.launch == LaunchType::Login;
+    if reset {
+        // MAIL is set based on the target user, unless it is preserved from the user's environment
+        if login || !environment.contains_key(OsStr::new("MAIL")) {
+            environment.insert(
+                "MAIL".into(),
+                format!("{PATH_MAILDIR}/{}", context.target_user.name).into(),
+            );
+        }
+        // The current SHELL variable should determine the shell to run when -s is passed, if none set use passwd entry
+        environment.insert("SHELL".into(), context.target_user.shell.clone().into());
     }
-    // The current SHELL variable should determine the shell to run when -s is passed, if none set use passwd entry
-    environment.insert("SHELL".into(), context.target_user.shell.clone().into());
-    // HOME' Set to the home directory of the target user if -i or -H are specified, env_reset or always_set_home are
-    // set in sudoers, or when the -s option is specified and set_home is set in sudoers.
-    // Since we always want to do env_reset -> always set HOME
-    if let Entry::Vacant(entry) = environment.entry("HOME".into()) {
-        entry.insert(context.target_user.home.clone().into());
+    // HOME is set to the home directory of the target user if -i or -H are specified, if
+    // always_set_home is set in sudoers, or when the -s option is specified and set_home is set
+    // in sudoers. Otherwise, if env_reset is enabled, it is only kept if listed in env_keep.
+    let set_home = context.set_home
+        || login
+        || cfg.always_set_home()
+        || (context.launch == LaunchType::Shell && cfg.set_home());
+    if set_home || (reset && !environment.contains_key(OsStr::new("HOME"))) {
+        environment.insert("HOME".into(), context.target_user.home.clone().into());
     }
 
     match (
         environment.get(OsStr::new("LOGNAME")),
         environment.get(OsStr::new("USER")),
     ) {
-        // Set to the login name of the target user when the -i option is specified,
-        // when the set_logname option is enabled in sudoers, or when the env_reset option
-        // is enabled in sudoers (unless LOGNAME is present in the env_keep list).
-        // Since we always want to do env_reset -> always set these except when present in env
+        // Set to the login name of the target user when the -i option is specified, or when the
+        // set_logname option is enabled in sudoers and env_reset is disabled; LOGIN is only
+        // changed if it is already present, as it is on some systems.
+        _ if login || (!reset && cfg.set_logname()) => {
+            environment.insert("LOGNAME".into(), context.target_user.name.clone().into());
+            environment.insert("USER".into(), context.target_user.name.clone().into());
+            if let Entry::Occupied(mut entry) = environment.entry("LOGIN".into()) {
+                entry.insert(context.target_user.name.clone().into());
+            }
+        }
+        // If the environment was not reset, the invoking user's values are kept.
+        _ if !reset => {}
+        // When the env_reset option is enabled in sudoers, these are set unless LOGNAME or USER
+        // is present in the env_keep list.
         (None, None) => {
             environment.insert("LOGNAME".into(), context.target_user.name.clone().into());
             environment.insert("USER".into(), context.target_user.name.clone().into());
@@ -97,12 +121,12 @@ fn add_extra_env(
         environment.insert("PATH".into(), secure_path.into());
     }
     // If the PATH and TERM variables are not preserved from the user's environment, they will be set to default value
-    if !environment.contains_key(OsStr::new("PATH")) {
+    if reset && !environment.contains_key(OsStr::new("PATH")) {
         // If the PATH variable is not set, it will be set to default value
         environment.insert("PATH".into(), PATH_DEFAULT.into());
     }
     // If the TERM variable is not preserved from the user's environment, it will be set to default value
-    if !environment.contains_key(OsStr::new("TERM")) {
+    if reset && !environment.contains_key(OsStr::new("TERM")) {
         environment.insert("TERM".into(), "unknown".into());
     }
     // The SUDO_PS1 variable requires special treatment as the PS1 variable must be set in the
@@ -149,29 +173,136 @@ fn is_safe_tz(value: &[u8]) -> bool {
         && check_value.len() < PATH_MAX as usize
 }
 
-/// Check whether the needle exists in a haystack, in which the haystack is a list of patterns, possibly containing wildcards
-fn in_table(needle: &OsStr, haystack: &HashSet<String>) -> bool {
+/// Check whether a variable matches a pattern, possibly containing wildcards; a pattern of the
+/// form `NAME=value` only matches a variable with that name and value
+fn matches_pattern(key: &OsStr, value: &OsStr, pattern: &str) -> bool {
+    match pattern.split_once('=') {
+        Some((name, value_pattern)) => {
+            wildcard_match(key.as_bytes(), name.as_bytes())
+                && wildcard_match(value.as_bytes(), value_pattern.as_bytes())
+        }
+        None => wildcard_match(key.as_bytes(), pattern.as_bytes()),
+    }
+}
+
+/// Check whether a variable matches a list of patterns
+fn in_table(key: &OsStr, value: &OsStr, haystack: &HashSet<String>) -> bool {
     haystack
         .iter()
-        .any(|pattern| wildcard_match(needle.as_bytes(), pattern.as_bytes()))
+        .any(|pattern| matches_pattern(key, value, pattern))
+}
+
+/// Check whether a variable matches a pattern of a list that also restricts its value; a value
+/// pattern of just `*` does not count
+fn value_in_table(key: &OsStr, value: &OsStr, haystack: &HashSet<String>) -> bool {
+    haystack.iter().any(|pattern| {
+        pattern
+            .split_once('=')
+            .is_some_and(|(_, value_pattern)| value_pattern != "*")
+            && matches_pattern(key, value, pattern)
+    })
 }
 
 /// Determine whether a specific environment variable should be kept
 fn should_keep(key: &OsStr, value: &OsStr, cfg: &impl Policy) -> bool {
-    if value.as_bytes().starts_with("()".as_bytes()) {
+    // like the original sudo, bash functions are only kept if their value was matched as well
+    if value.as_bytes().starts_with("()".as_bytes())
+        && !value_in_table(key, value, cfg.env_check())
+        && !value_in_table(key, value, cfg.env_keep())
+    {
         return false;
     }
 
     if key == "TZ" {
-        return in_table(key, cfg.env_keep())
-            || (in_table(key, cfg.env_check()) && is_safe_tz(value.as_bytes()));
+        return in_table(key, value, cfg.env_keep())
+            || (in_table(key, value, cfg.env_check()) && is_safe_tz(value.as_bytes()));
     }
 
-    if in_table(key, cfg.env_check()) {
+    if in_table(key, value, cfg.env_check()) {
         return !value.as_bytes().iter().any(|c| *c == b'%' || *c == b'/');
     }
 
-    in_table(key, cfg.env_keep())
+    in_table(key, value, cfg.env_keep())
+}
+
+/// Determine whether the user may set a specific environment variable on the command line, if
+/// the policy does not allow setting arbitrary variables
+fn may_set(key: &OsStr, value: &OsStr, cfg: &impl Policy) -> bool {
+    // the PATH of the command is determined by secure_path
+    if key == "PATH" && cfg.secure_path().is_some() {
+        return false;
+    }
+
+    if cfg.env_reset() {
+        should_keep(key, value, cfg)
+    } else {
+        should_not_delete(key, value, cfg)
+    }
+}
+
+/// Determine whether a specific environment variable should be kept if the environment is not
+/// reset; i.e. it is not in env_delete and passes env_check (if it is listed there)
+fn should_not_delete(key: &OsStr, value: &OsStr, cfg: &impl Policy) -> bool {
+    !value.as_bytes().starts_with("()".as_bytes())
+        && !in_table(key, value, cfg.env_delete())
+        && (!in_table(key, value, cfg.env_check()) || should_keep(key, value, cfg))
+}
+
+/// Remove leading and trailing ASCII whitespace from a byte slice
+fn trim_whitespace(mut bytes: &[u8]) -> &[u8] {
+    while let [first, rest @ ..] = bytes {
+        if !first.is_ascii_whitespace() {
+            break;
+        }
+        bytes = rest;
+    }
+    while let [rest @ .., last] = bytes {
+        if !last.is_ascii_whitespace() {
+            break;
+        }
+        bytes = rest;
+    }
+
+    bytes
+}
+
+/// Parse the contents of an env_file: lines of the form `VARIABLE=value` or
+/// `export VARIABLE=value`, in which the value can be enclosed in single or double quotes.
+/// Empty lines, comments and other malformed lines are skipped.
+fn parse_env_file(text: &[u8]) -> Vec<(OsString, OsString)> {
+    text.split(|c| *c == b'\n')
+        .filter_map(|line| {
+            let line = trim_whitespace(line);
+            if line.starts_with(b"#") {
+                return None;
+            }
+            let line = trim_whitespace(line.strip_prefix(b"export ").unwrap_or(line));
+            let pos = line.iter().position(|c| *c == b'=')?;
+            let (key, value) = (&line[..pos], &line[pos + 1..]);
+            if key.is_empty() {
+                return None;
+            }
+            let value = match value {
+                [quote @ (b'"' | b'\''), inner @ .., end] if quote == end => inner,
+                _ => value,
+            };
+
+            Some((
+                OsStr::from_bytes(key).to_owned(),
+                OsStr::from_bytes(value).to_owned(),
+            ))
+        })
+        .collect()
+}
+
+/// Read the variables in an env_file; a file that does not exist does not contain any
+fn read_env_file(path: &str) -> Result<Vec<(OsString, OsString)>, Error> {
+    let mut text = Vec::new();
+    match secure_open(path).and_then(|mut file| file.read_to_end(&mut text)) {
+        Ok(_) => Ok(parse_env_file(&text)),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(Vec::new()),
+        Err(err) => Err(Error::Configuration(format!("cannot read '{path}': {err}"))),
+    }
 }
 
 /// Construct the final environment from the current one and a sudo context
@@ -187,36 +318,113 @@ fn should_keep(key: &OsStr, value: &OsStr, cfg: &impl Policy) -> bool {
 /// If the PATH and TERM variables are not preserved from the user's environment, they will be set to default value
 ///
 /// Environment variables with a value beginning with ‘()’ are removed
+///
+/// If env_reset is disabled, or when the user asks to preserve the environment (`-E`), which
+/// requires the policy to allow the user to set any variable, the environment is not reset: only
+/// the variables that are listed in env_delete or that fail env_check are removed. In that case
+/// MAIL, SHELL, PATH and TERM are inherited as they are, and LOGNAME and USER are set to the
+/// target user if set_logname is enabled. The environment of a login shell (`-i`) is always reset.
+///
+/// HOME is set to the home directory of the target user with `-H` or `-i`, or if always_set_home
+/// is enabled, or for a shell (`-s`) if set_home is enabled; otherwise it is only set if the
+/// environment is reset and HOME is not in env_keep.
+///
+/// Variables from the restricted_env_file and then the env_file are added if they are not
+/// already present; the first has to pass the same checks as the current environment, while
+/// the latter is trusted as part of the policy.
+///
+/// Variables that the user has set on the command line or asked to preserve by name are added
+/// last; unless the policy allows the user to set any variable, these have to pass the same
+/// checks as the current environment.
 pub fn get_target_environment(
     current_env: Environment,
     context: &Context,
     settings: &impl Policy,
-) -> Environment {
+) -> Result<Environment, Error> {
+    if context.short_preserve_env && !settings.setenv() {
+        return Err(Error::PreserveEnvironment);
+    }
+
     let mut environment = Environment::default();
 
     // retrieve SUDO_PS1 value to set a PS1 value as additional environment
     let sudo_ps1 = current_env.get(OsStr::new("SUDO_PS1")).cloned();
 
-    environment.extend(
-        current_env
-            .into_iter()
-            .filter(|(key, value)| should_keep(key, value, settings)),
-    );
+    // variables that are preserved by name are treated as if they were set on the command line
+    let user_vars = context
+        .preserve_env
+        .iter()
+        .filter_map(|key| Some((key.into(), current_env.get(OsStr::new(key))?.clone())))
+        .chain(
+            context
+                .env_var_list
+                .iter()
+                .map(|(key, value)| (key.into(), value.into())),
+        )
+        .collect::<Vec<(OsString, OsString)>>();
+
+    // a login shell always gets a fresh environment, as if env_reset is enabled
+    let reset = context.launch == LaunchType::Login
+        || (settings.env_reset() && !context.short_preserve_env);
+
+    environment.extend(current_env.into_iter().filter(|(key, value)| {
+        if reset {
+            should_keep(key, value, settings)
+        } else {
+            should_not_delete(key, value, settings)
+        }
+    }));
+
+    add_extra_env(context, settings, reset, sudo_ps1, &mut environment);
+
+    if let Some(path) = settings.restricted_env_file() {
+        for (key, value) in read_env_file(&path)? {
+            let allowed = if reset {
+                should_keep(&key, &value, settings)
+            } else {
+                should_not_delete(&key, &value, settings)
+            };
+            if allowed {
+                environment.entry(key).or_insert(value);
+            }
+        }
+    }
+
+    if let Some(path) = settings.env_file() {
+        for (key, value) in read_env_file(&path)? {
+            environment.entry(key).or_insert(value);
+        }
+    }
+
+    if !settings.setenv() {
+        let forbidden = user_vars
+            .iter()
+            .filter(|(key, value)| !may_set(key, value, settings))
+            .map(|(key, _)| key.to_string_lossy().into_owned())
+            .collect::<Vec<_>>();
+        if !forbidden.is_empty() {
+            return Err(Error::EnvironmentVar(forbidden));
+        }
+    }
 
-    add_extra_env(context, settings, sudo_ps1, &mut environment);
+    environment.extend(user_vars);
 
-    environment
+    Ok(environment)
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{is_safe_tz, should_keep, PATH_ZONEINFO};
+    use super::{
+        is_safe_tz, may_set, parse_env_file, should_keep, should_not_delete, PATH_ZONEINFO,
+    };
     use crate::sudoers::Policy;
     use std::{collections::HashSet, ffi::OsStr};
 
     struct TestConfiguration {
         keep: HashSet<String>,
         check: HashSet<String>,
+        delete: HashSet<String>,
+        reset: bool,
     }
 
     impl Policy for TestConfiguration {
@@ -228,9 +436,25 @@ mod tests {
             &self.check
         }
 
+        fn env_delete(&self) -> &HashSet<String> {
+            &self.delete
+        }
+
+        fn env_reset(&self) -> bool {
+            self.reset
+        }
+
         fn secure_path(&self) -> Option<String> {
             None
         }
+
+        fn editor(&self) -> String {
+            String::new()
+        }
+
+        fn env_editor(&self) -> bool {
+            true
+        }
     }
 
     #[test]
@@ -238,6 +462,8 @@ mod tests {
         let config = TestConfiguration {
             keep: HashSet::from(["AAP".to_string(), "NOOT".to_string()]),
             check: HashSet::from(["MIES".to_string(), "TZ".to_string()]),
+            delete: HashSet::new(),
+            reset: true,
         };
 
         let check_should_keep = |key: &str, value: &str, expected: bool| {
@@ -257,6 +483,134 @@ mod tests {
         check_should_keep("TZ", "../Europe/Berlin", false);
         check_should_keep("MIES", "FOO/BAR", false);
         check_should_keep("MIES", "FOO%", false);
+
+        let config = TestConfiguration {
+            keep: HashSet::from(["AAP=FOO".to_string(), "LC_*=en_*".to_string()]),
+            check: HashSet::from(["MIES=*".to_string()]),
+            delete: HashSet::new(),
+            reset: true,
+        };
+
+        assert!(should_keep(OsStr::new("AAP"), OsStr::new("FOO"), &config));
+        assert!(!should_keep(OsStr::new("AAP"), OsStr::new("BAR"), &config));
+        assert!(should_keep(
+            OsStr::new("LC_ALL"),
+            OsStr::new("en_US"),
+            &config
+        ));
+        assert!(!should_keep(
+            OsStr::new("LC_ALL"),
+            OsStr::new("nl_NL"),
+            &config
+        ));
+        assert!(should_keep(OsStr::new("MIES"), OsStr::new("BAR"), &config));
+        assert!(!should_keep(
+            OsStr::new("MIES"),
+            OsStr::new("FOO/BAR"),
+            &config
+        ));
+        assert!(!should_keep(
+            OsStr::new("MIES"),
+            OsStr::new("() 42"),
+            &config
+        ));
+
+        // bash functions are only kept if a pattern explicitly matches their value
+        let config = TestConfiguration {
+            keep: HashSet::from(["AAP=()*".to_string()]),
+            check: HashSet::new(),
+            delete: HashSet::new(),
+            reset: true,
+        };
+
+        assert!(should_keep(OsStr::new("AAP"), OsStr::new("() 42"), &config));
+        assert!(!should_keep(
+            OsStr::new("NOOT"),
+            OsStr::new("() 42"),
+            &config
+        ));
+    }
+
+    #[test]
+    fn test_setting_variables() {
+        let mut config = TestConfiguration {
+            keep: HashSet::from(["AAP".to_string()]),
+            check: HashSet::from(["MIES".to_string()]),
+            delete: HashSet::from(["NOOT".to_string()]),
+            reset: true,
+        };
+
+        let may_set = |key: &str, value: &str, config: &TestConfiguration| {
+            may_set(OsStr::new(key), OsStr::new(value), config)
+        };
+
+        assert!(may_set("AAP", "FOO", &config));
+        assert!(may_set("MIES", "BAR", &config));
+        assert!(!may_set("MIES", "FOO/BAR", &config));
+        assert!(!may_set("NOOT", "BAR", &config));
+        assert!(!may_set("WIM", "BAR", &config));
+
+        assert!(should_not_delete(
+            OsStr::new("WIM"),
+            OsStr::new("BAR"),
+            &config
+        ));
+        assert!(!should_not_delete(
+            OsStr::new("WIM"),
+            OsStr::new("()"),
+            &config
+        ));
+
+        config.delete.insert("*=()*".to_string());
+        assert!(!should_not_delete(
+            OsStr::new("BASH_FUNC_foo%%"),
+            OsStr::new("() {  echo foo\n}"),
+            &config
+        ));
+        config.check.insert("WIM".to_string());
+        assert!(!should_not_delete(
+            OsStr::new("WIM"),
+            OsStr::new("BAR/BAZ"),
+            &config
+        ));
+        config.check.remove("WIM");
+
+        config.reset = false;
+        assert!(may_set("AAP", "FOO", &config));
+        assert!(may_set("MIES", "BAR", &config));
+        assert!(!may_set("MIES", "FOO/BAR", &config));
+        assert!(!may_set("NOOT", "BAR", &config));
+        assert!(may_set("WIM", "BAR", &config));
+    }
+
+    #[test]
+    fn test_parse_env_file() {
+        let vars = parse_env_file(
+            b"# proxy settings\n\
+              http_proxy=http://proxy:3128\n\
+              export  LANG=\"en_US.UTF-8\"\n\
+              \n\
+              \tCA='/etc/ssl/ca bundle.pem'\n\
+              EMPTY=\n\
+              QUOTE=\"unbalanced'\n\
+              malformed line\n\
+              =value\n",
+        );
+        let vars = vars
+            .iter()
+            .map(|(key, value)| (key.to_str().unwrap(), value.to_str().unwrap()))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            vars,
+            [
+                ("http_proxy", "http://proxy:3128"),
+                ("LANG", "en_US.UTF-8"),
+                ("CA", "/etc/ssl/ca bundle.pem"),
+                ("EMPTY", ""),
+                ("QUOTE", "\"unbalanced'"),
+            ]
+        );
     }
 
     #[allow(clippy::useless_format)]
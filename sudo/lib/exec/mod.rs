@@ -8,13 +8,18 @@ mod parent;
 
 use std::{
     ffi::{CString, OsStr},
+    fs::File,
     io,
     os::unix::ffi::OsStrExt,
+    os::unix::fs::FileExt,
+    os::unix::io::AsRawFd,
     os::unix::process::CommandExt,
+    path::Path,
     process::Command,
 };
 
 use crate::common::{context::LaunchType::Login, Context, Environment};
+use crate::cutils::cerr;
 use crate::log::user_error;
 use crate::system::set_target_user;
 use parent::exec_pty;
@@ -25,7 +30,10 @@ use parent::exec_pty;
 /// signals once its called.
 pub fn run_command(ctx: Context, env: Environment) -> io::Result<(ExitReason, impl FnOnce())> {
     // FIXME: should we pipe the stdio streams?
-    let mut command = Command::new(&ctx.command.command);
+    let mut command = match &ctx.command_file {
+        Some(file) => command_from_file(file, &ctx.command.command),
+        None => Command::new(&ctx.command.command),
+    };
     // reset env and set filtered environment
     command.args(ctx.command.arguments).env_clear().envs(env);
     // Decide if the pwd should be changed. `--chdir` takes precedence over `-i`.
@@ -73,6 +81,25 @@ pub fn run_command(ctx: Context, env: Environment) -> io::Result<(ExitReason, im
     exec_pty(ctx.process.pid, command)
 }
 
+/// Execute the already opened `file` instead of looking up its `path` again, so that the file
+/// cannot be replaced in between; like `fexecve(3)`, this uses the `/proc` filesystem.
+fn command_from_file(file: &File, path: &Path) -> Command {
+    let fd = file.as_raw_fd();
+    let mut command = Command::new(format!("/proc/self/fd/{fd}"));
+    command.arg0(path);
+
+    // the interpreter of a script opens the script by its path, so it must be kept open
+    let mut magic = [0; 2];
+    if file.read_at(&mut magic, 0).ok() == Some(2) && &magic == b"#!" {
+        #[allow(unsafe_code)]
+        unsafe {
+            command.pre_exec(move || cerr(libc::fcntl(fd, libc::F_SETFD, 0)).map(|_| ()));
+        }
+    }
+
+    command
+}
+
 /// Exit reason for the command executed by sudo.
 #[derive(Debug)]
 pub enum ExitReason {
@@ -1,7 +1,10 @@
 pub mod cli;
 pub mod common;
+pub mod config;
 pub mod cutils;
 pub mod defaults;
+pub mod diagnostic;
+pub mod edit;
 pub mod env;
 pub mod exec;
 pub mod log;
@@ -1,8 +1,14 @@
+use std::io;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
 use crate::cutils::string_from_ptr;
+use crate::system::{set_target_user, Group, User};
 
 use super::sys::*;
 
-use super::{error::PamResult, rpassword, securemem::PamBuffer, PamErrorType};
+use super::{error::PamResult, rpassword, securemem::PamBuffer, PamError, PamErrorType};
 
 /// Each message in a PAM conversation will have a message style. Each of these
 /// styles must be handled separately.
@@ -83,6 +89,10 @@ pub trait Converser {
     /// Handle all the message in the given conversation. They may all be
     /// handled in sequence or at the same time if possible.
     fn handle_conversation(&self, conversation: &mut Conversation) -> PamResult<()>;
+
+    /// Show a text to the user as it is, outside of a PAM conversation; this is used for the
+    /// lecture.
+    fn show_text(&self, text: &str) -> PamResult<()>;
 }
 
 pub trait SequentialConverser: Converser {
@@ -101,6 +111,9 @@ pub trait SequentialConverser: Converser {
     /// Display an informational message to the user, the user does not need to
     /// input a value.
     fn handle_info(&self, msg: &str) -> PamResult<()>;
+
+    /// Display a text to the user without any decoration.
+    fn handle_text(&self, text: &str) -> PamResult<()>;
 }
 
 impl<T> Converser for T
@@ -129,12 +142,21 @@ where
 
         Ok(())
     }
+
+    fn show_text(&self, text: &str) -> PamResult<()> {
+        self.handle_text(text)
+    }
 }
 
 /// A converser that uses stdin/stdout/stderr to display messages and to request
 /// input from the user.
 pub struct CLIConverser {
     pub(super) use_stdin: bool,
+    pub(super) password_timeout: Option<Duration>,
+    /// A custom prompt to show instead of the one PAM provides for passwords
+    pub(super) prompt: Option<String>,
+    /// Also show the custom prompt if PAM does not use its standard password prompt
+    pub(super) prompt_override: bool,
 }
 
 use rpassword::Terminal;
@@ -147,19 +169,38 @@ impl CLIConverser {
             Terminal::open_tty()
         }
     }
+
+    fn password_prompt(&self, msg: &str) -> String {
+        match custom_prompt(&self.prompt, self.prompt_override, msg) {
+            Some(prompt) => prompt.to_string(),
+            None => format!("[Sudo: authenticate] {msg}"),
+        }
+    }
+}
+
+/// The custom prompt that should be shown instead of the password prompt `msg` of PAM, if any
+fn custom_prompt<'a>(
+    prompt: &'a Option<String>,
+    prompt_override: bool,
+    msg: &str,
+) -> Option<&'a str> {
+    match prompt {
+        Some(prompt) if prompt_override || msg == "Password: " => Some(prompt),
+        _ => None,
+    }
 }
 
 impl SequentialConverser for CLIConverser {
     fn handle_normal_prompt(&self, msg: &str) -> PamResult<PamBuffer> {
         let mut tty = self.open()?;
         tty.prompt(&format!("[Sudo: input needed] {msg} "))?;
-        Ok(tty.read_cleartext()?)
+        Ok(tty.read_cleartext(self.password_timeout)?)
     }
 
     fn handle_hidden_prompt(&self, msg: &str) -> PamResult<PamBuffer> {
         let mut tty = self.open()?;
-        tty.prompt(&format!("[Sudo: authenticate] {msg}"))?;
-        Ok(tty.read_password()?)
+        tty.prompt(&self.password_prompt(msg))?;
+        Ok(tty.read_password(self.password_timeout)?)
     }
 
     fn handle_error(&self, msg: &str) -> PamResult<()> {
@@ -171,12 +212,138 @@ impl SequentialConverser for CLIConverser {
         let mut tty = self.open()?;
         Ok(tty.prompt(&format!("[Sudo] {msg}\n"))?)
     }
+
+    fn handle_text(&self, text: &str) -> PamResult<()> {
+        let mut tty = self.open()?;
+        Ok(tty.prompt(text)?)
+    }
+}
+
+/// A converser that runs a helper program to ask the user for input, so that no terminal is
+/// needed. The helper is run as the invoking user with the prompt as its only argument, and
+/// should print the answer on its standard output.
+pub struct AskpassConverser {
+    pub(super) program: PathBuf,
+    pub(super) user: User,
+    pub(super) password_timeout: Option<Duration>,
+    pub(super) prompt: Option<String>,
+    pub(super) prompt_override: bool,
+}
+
+impl AskpassConverser {
+    fn ask(&self, prompt: &str) -> io::Result<PamBuffer> {
+        // the helper is not looked up in the PATH of the user
+        if !self.program.is_absolute() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "askpass program '{}' is not an absolute path",
+                    self.program.display()
+                ),
+            ));
+        }
+
+        let group = Group::from_gid(self.user.gid)?.ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("group {} not found", self.user.gid),
+            )
+        })?;
+
+        let mut command = Command::new(&self.program);
+        command
+            .arg(prompt)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped());
+        set_target_user(&mut command, self.user.clone(), group);
+
+        let mut child = command.spawn()?;
+        let answer = match (child.stdout.take(), self.password_timeout) {
+            (Some(mut stdout), Some(timeout)) => {
+                rpassword::read_unbuffered(&mut rpassword::TimeoutRead::new(&mut stdout, timeout))
+            }
+            (Some(mut stdout), None) => rpassword::read_unbuffered(&mut stdout),
+            (None, _) => Err(io::ErrorKind::BrokenPipe.into()),
+        };
+
+        // the helper may still be waiting for the user if no answer was read in time
+        if answer.is_err() {
+            let _ = child.kill();
+        }
+
+        let status = child.wait()?;
+        let answer = answer?;
+
+        // the helper exits unsuccessfully if the user cancelled the question
+        if !status.success() {
+            return Err(io::Error::other("no password was provided"));
+        }
+
+        Ok(answer)
+    }
+}
+
+impl SequentialConverser for AskpassConverser {
+    fn handle_normal_prompt(&self, msg: &str) -> PamResult<PamBuffer> {
+        Ok(self.ask(msg)?)
+    }
+
+    fn handle_hidden_prompt(&self, msg: &str) -> PamResult<PamBuffer> {
+        let prompt = custom_prompt(&self.prompt, self.prompt_override, msg).unwrap_or(msg);
+        Ok(self.ask(prompt)?)
+    }
+
+    fn handle_error(&self, msg: &str) -> PamResult<()> {
+        eprintln!("[Sudo error] {msg}");
+        Ok(())
+    }
+
+    fn handle_info(&self, msg: &str) -> PamResult<()> {
+        eprintln!("[Sudo] {msg}");
+        Ok(())
+    }
+
+    fn handle_text(&self, text: &str) -> PamResult<()> {
+        eprint!("{text}");
+        Ok(())
+    }
+}
+
+/// A converser for when the user may not be asked for anything, as with `sudo -n`: every prompt
+/// fails with [PamError::InteractionRequired], but messages are still shown on stderr.
+pub struct NonInteractiveConverser;
+
+impl SequentialConverser for NonInteractiveConverser {
+    fn handle_normal_prompt(&self, _msg: &str) -> PamResult<PamBuffer> {
+        Err(PamError::InteractionRequired)
+    }
+
+    fn handle_hidden_prompt(&self, _msg: &str) -> PamResult<PamBuffer> {
+        Err(PamError::InteractionRequired)
+    }
+
+    fn handle_error(&self, msg: &str) -> PamResult<()> {
+        eprintln!("[Sudo error] {msg}");
+        Ok(())
+    }
+
+    fn handle_info(&self, msg: &str) -> PamResult<()> {
+        eprintln!("[Sudo] {msg}");
+        Ok(())
+    }
+
+    fn handle_text(&self, text: &str) -> PamResult<()> {
+        eprint!("{text}");
+        Ok(())
+    }
 }
 
-/// Helper struct that contains the converser as well as panic boolean
+/// Helper struct that contains the converser as well as panic boolean, and the error that made
+/// the converser give up, if any
 pub(super) struct ConverserData<C> {
     pub(super) converser: C,
     pub(super) panicked: bool,
+    pub(super) error: Option<PamError>,
 }
 
 /// This function implements the conversation function of `pam_conv`.
@@ -224,11 +391,16 @@ pub(super) unsafe extern "C" fn converse<C: Converser>(
 
         // send the conversation of to the Rust part
         let app_data = unsafe { &mut *(appdata_ptr as *mut ConverserData<C>) };
-        if app_data
-            .converser
-            .handle_conversation(&mut conversation)
-            .is_err()
-        {
+        if let Err(err) = app_data.converser.handle_conversation(&mut conversation) {
+            // only these errors end the authentication; any other failure of the converser is
+            // reported by PAM as a failed attempt
+            app_data.error = match err {
+                PamError::IoError(err) if err.kind() == io::ErrorKind::TimedOut => {
+                    Some(PamError::TimedOut)
+                }
+                PamError::InteractionRequired => Some(PamError::InteractionRequired),
+                _ => None,
+            };
             return PamErrorType::ConversationError;
         }
 
@@ -297,6 +469,10 @@ mod test {
         fn handle_info(&self, _msg: &str) -> PamResult<()> {
             Ok(())
         }
+
+        fn handle_text(&self, _text: &str) -> PamResult<()> {
+            Ok(())
+        }
     }
 
     // essentially do the inverse of the "conversation function"
@@ -397,6 +573,7 @@ mod test {
         let mut hello = Box::pin(ConverserData {
             converser: "tux".to_string(),
             panicked: false,
+            error: None,
         });
         let cookie = PamConvBorrow::new(hello.as_mut());
         let pam_conv = cookie.borrow();
@@ -439,4 +616,24 @@ mod test {
 
         assert!(hello.panicked); // allowed now
     }
+
+    #[test]
+    fn failing_converser_stores_error() {
+        let mut data = Box::pin(ConverserData {
+            converser: NonInteractiveConverser,
+            panicked: false,
+            error: None,
+        });
+        let cookie = PamConvBorrow::new(data.as_mut());
+        let pam_conv = cookie.borrow();
+
+        assert_eq!(dummy_pam(&[msg(TextInfo, "hello")], pam_conv), vec![None]);
+        assert_eq!(
+            dummy_pam(&[msg(PromptEchoOff, "Password: ")], pam_conv),
+            vec![]
+        );
+
+        assert!(matches!(data.error, Some(PamError::InteractionRequired)));
+        assert!(!data.panicked);
+    }
 }
@@ -191,6 +191,8 @@ pub enum PamError {
     SessionAlreadyOpen,
     SessionNotOpen,
     EnvListFailure,
+    TimedOut,
+    InteractionRequired,
 }
 
 impl From<std::io::Error> for PamError {
@@ -227,6 +229,8 @@ impl fmt::Display for PamError {
                     "It was not possible to get a list of environment variables"
                 )
             }
+            PamError::TimedOut => write!(f, "timed out reading password"),
+            PamError::InteractionRequired => write!(f, "a password is required"),
         }
     }
 }
@@ -1,9 +1,11 @@
 use std::{
     ffi::{CStr, CString},
+    path::PathBuf,
     time::Duration,
 };
 
 use crate::cutils::string_from_ptr;
+use crate::system::User;
 use converse::ConverserData;
 use error::pam_err;
 pub use error::{PamError, PamErrorType, PamResult};
@@ -17,7 +19,7 @@ mod securemem;
 #[allow(nonstandard_style)]
 pub mod sys;
 
-pub use converse::{CLIConverser, Converser};
+pub use converse::{AskpassConverser, CLIConverser, Converser, NonInteractiveConverser};
 
 pub struct PamContext<C: Converser> {
     data_ptr: *mut ConverserData<C>,
@@ -73,6 +75,7 @@ impl<C: Converser> PamContextBuilder<C> {
             let data_ptr = Box::into_raw(Box::new(ConverserData {
                 converser,
                 panicked: false,
+                error: None,
             }));
 
             let mut pamh = std::ptr::null_mut();
@@ -185,13 +188,20 @@ impl<C: Converser> PamContext<C> {
         }
     }
 
-    /// Run authentication for the account
+    /// Run authentication for the account; if the converser gave up with [PamError::TimedOut]
+    /// because the user did not answer a prompt in time, or with [PamError::InteractionRequired],
+    /// that error is returned regardless of how the PAM modules reported it.
     pub fn authenticate(&mut self) -> PamResult<()> {
         let mut flags = 0;
         flags |= self.silent_flag();
         flags |= self.disallow_null_auth_token_flag();
 
-        pam_err(unsafe { pam_authenticate(self.pamh, flags) })
+        let result = pam_err(unsafe { pam_authenticate(self.pamh, flags) });
+        if let Some(err) = unsafe { (*self.data_ptr).error.take() } {
+            return Err(err);
+        }
+
+        result
     }
 
     /// Check that the account is valid
@@ -411,12 +421,58 @@ impl<C: Converser> PamContext<C> {
     pub fn has_panicked(&self) -> bool {
         unsafe { (*self.data_ptr).panicked }
     }
+
+    /// Show a text to the user through the converser, such as the lecture
+    pub fn show_text(&self, text: &str) -> PamResult<()> {
+        unsafe { (*self.data_ptr).converser.show_text(text) }
+    }
 }
 
 impl PamContext<CLIConverser> {
-    /// Create a builder that uses the CLI conversation function.
-    pub fn builder_cli(use_stdin: bool) -> PamContextBuilder<CLIConverser> {
-        PamContextBuilder::default().converser(CLIConverser { use_stdin })
+    /// Create a builder that uses the CLI conversation function; prompts on the terminal fail
+    /// if the user does not answer them within `password_timeout`. If a `prompt` is given, it
+    /// replaces the standard password prompt of PAM, or any password prompt if `prompt_override`
+    /// is set.
+    pub fn builder_cli(
+        use_stdin: bool,
+        password_timeout: Option<Duration>,
+        prompt: Option<String>,
+        prompt_override: bool,
+    ) -> PamContextBuilder<CLIConverser> {
+        PamContextBuilder::default().converser(CLIConverser {
+            use_stdin,
+            password_timeout,
+            prompt,
+            prompt_override,
+        })
+    }
+}
+
+impl PamContext<AskpassConverser> {
+    /// Create a builder that asks for input by running the helper `program` as `user`; the
+    /// helper is killed if it does not answer within `password_timeout`. A `prompt` is used as
+    /// for [PamContext::builder_cli].
+    pub fn builder_askpass(
+        program: PathBuf,
+        user: User,
+        password_timeout: Option<Duration>,
+        prompt: Option<String>,
+        prompt_override: bool,
+    ) -> PamContextBuilder<AskpassConverser> {
+        PamContextBuilder::default().converser(AskpassConverser {
+            program,
+            user,
+            password_timeout,
+            prompt,
+            prompt_override,
+        })
+    }
+}
+
+impl PamContext<NonInteractiveConverser> {
+    /// Create a builder whose conversation function fails if PAM needs any input
+    pub fn builder_non_interactive() -> PamContextBuilder<NonInteractiveConverser> {
+        PamContextBuilder::default().converser(NonInteractiveConverser)
     }
 }
 
@@ -15,11 +15,13 @@
 ///
 use std::io::{self, Error, ErrorKind, Read};
 use std::os::fd::{AsRawFd, RawFd};
+use std::time::{Duration, Instant};
 use std::{fs, mem};
 
 use libc::{tcsetattr, termios, ECHO, ECHONL, TCSANOW};
 
 use crate::cutils::cerr;
+use crate::system::poll::PollSet;
 
 use super::securemem::PamBuffer;
 
@@ -73,7 +75,7 @@ fn safe_tcgetattr(fd: RawFd) -> io::Result<termios> {
 }
 
 /// Reads a password from the given file descriptor
-fn read_unbuffered(source: &mut impl io::Read) -> io::Result<PamBuffer> {
+pub(super) fn read_unbuffered(source: &mut impl io::Read) -> io::Result<PamBuffer> {
     let mut password = PamBuffer::default();
     let mut pwd_iter = password.iter_mut();
 
@@ -94,6 +96,37 @@ fn read_unbuffered(source: &mut impl io::Read) -> io::Result<PamBuffer> {
     Ok(password)
 }
 
+/// A reader that fails if no input is available before a deadline
+pub(super) struct TimeoutRead<'a, R> {
+    source: &'a mut R,
+    deadline: Instant,
+}
+
+impl<'a, R: io::Read + AsRawFd> TimeoutRead<'a, R> {
+    pub(super) fn new(source: &'a mut R, timeout: Duration) -> TimeoutRead<'a, R> {
+        TimeoutRead {
+            source,
+            deadline: Instant::now() + timeout,
+        }
+    }
+}
+
+impl<R: io::Read + AsRawFd> io::Read for TimeoutRead<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let remaining = self.deadline.saturating_duration_since(Instant::now());
+        let mut poll_set = PollSet::new();
+        poll_set.add_fd_read((), self.source);
+        if poll_set.poll_timeout(Some(remaining))?.is_empty() {
+            return Err(Error::new(
+                ErrorKind::TimedOut,
+                "timed out reading password",
+            ));
+        }
+
+        self.source.read(buf)
+    }
+}
+
 /// Write something and immediately flush
 fn write_unbuffered(sink: &mut impl io::Write, text: &str) -> io::Result<()> {
     sink.write_all(text.as_bytes())?;
@@ -122,16 +155,25 @@ impl Terminal<'_> {
         Ok(Terminal::StdIE(io::stdin().lock(), io::stderr().lock()))
     }
 
-    /// Reads input with TTY echo disabled
-    pub fn read_password(&mut self) -> io::Result<PamBuffer> {
-        let mut input = self.source();
+    /// Reads input with TTY echo disabled; if a timeout is given, reading from the TTY fails
+    /// when the input is not complete in time (standard input is read without a timeout)
+    pub fn read_password(&mut self, timeout: Option<Duration>) -> io::Result<PamBuffer> {
         let _hide_input = HiddenInput::new()?;
-        read_unbuffered(&mut input)
+        self.read_with_timeout(timeout)
     }
 
     /// Reads input with TTY echo enabled
-    pub fn read_cleartext(&mut self) -> io::Result<PamBuffer> {
-        read_unbuffered(&mut self.source())
+    pub fn read_cleartext(&mut self, timeout: Option<Duration>) -> io::Result<PamBuffer> {
+        self.read_with_timeout(timeout)
+    }
+
+    fn read_with_timeout(&mut self, timeout: Option<Duration>) -> io::Result<PamBuffer> {
+        match (self, timeout) {
+            (Terminal::Tty(tty), Some(timeout)) => {
+                read_unbuffered(&mut TimeoutRead::new(tty, timeout))
+            }
+            (terminal, _) => read_unbuffered(&mut terminal.source()),
+        }
     }
 
     /// Display information
@@ -157,7 +199,9 @@ impl Terminal<'_> {
 
 #[cfg(test)]
 mod test {
-    use super::{read_unbuffered, write_unbuffered};
+    use super::{read_unbuffered, write_unbuffered, TimeoutRead};
+    use std::io::{ErrorKind, Write};
+    use std::time::Duration;
 
     #[test]
     fn miri_test_read() {
@@ -181,6 +225,20 @@ mod test {
         assert!(read_unbuffered(&mut "a".repeat(512).as_bytes()).is_err());
     }
 
+    #[test]
+    fn read_with_deadline() {
+        let (read_end, mut write_end) = std::os::unix::net::UnixStream::pair().unwrap();
+        let mut source = std::fs::File::from(std::os::fd::OwnedFd::from(read_end));
+
+        write_end.write_all(b"pass").unwrap();
+        let mut reader = TimeoutRead::new(&mut source, Duration::from_millis(50));
+        assert!(read_unbuffered(&mut reader).is_err_and(|err| err.kind() == ErrorKind::TimedOut));
+
+        write_end.write_all(b"word\n").unwrap();
+        let mut reader = TimeoutRead::new(&mut source, Duration::from_millis(50));
+        assert!(read_unbuffered(&mut reader).is_ok());
+    }
+
     #[test]
     fn miri_test_write() {
         let mut data = Vec::new();
@@ -1,5 +1,6 @@
 use super::ast_names::UserFriendly;
 use super::basic_parser::*;
+use super::digest::{Digest, DigestAlgorithm};
 use super::tokens::*;
 
 /// The Sudoers file allows negating items with the exclamation mark.
@@ -26,8 +27,19 @@ pub enum UserSpecifier {
     User(Identifier),
     Group(Identifier),
     NonunixGroup(Identifier),
+    Netgroup(String),
 }
 
+/// A hostspecifier is either a host name, or a netgroup
+#[cfg_attr(test, derive(Clone, Debug, PartialEq, Eq))]
+pub enum HostSpecifier {
+    Host(Hostname),
+    Netgroup(String),
+}
+
+/// A command can be restricted to executables that have one of the listed digests
+pub type Command = (SimpleCommand, Box<[Digest]>);
+
 /// The RunAs specification consists of a (possibly empty) list of userspecifiers, followed by a (possibly empty) list of groups.
 pub struct RunAs {
     pub users: SpecList<UserSpecifier>,
@@ -35,11 +47,12 @@ pub struct RunAs {
 }
 
 /// Commands in /etc/sudoers can have attributes attached to them, such as NOPASSWD, NOEXEC, ...
-#[derive(Clone)]
-#[cfg_attr(test, derive(Debug, PartialEq, Eq))]
+#[derive(Clone, PartialEq, Eq)]
+#[cfg_attr(test, derive(Debug))]
 pub struct Tag {
     pub passwd: bool,
     pub cwd: Option<ChDir>,
+    pub setenv: Option<bool>,
 }
 
 impl Default for Tag {
@@ -47,6 +60,7 @@ impl Default for Tag {
         Tag {
             passwd: true,
             cwd: None,
+            setenv: None,
         }
     }
 }
@@ -59,7 +73,17 @@ type PairVec<A, B> = Vec<(A, Vec<B>)>;
 
 pub struct PermissionSpec {
     pub users: SpecList<UserSpecifier>,
-    pub permissions: PairVec<SpecList<Hostname>, (Option<RunAs>, CommandSpec)>,
+    pub permissions: PairVec<SpecList<HostSpecifier>, (Option<RunAs>, CommandSpec)>,
+    pub origin: Origin,
+}
+
+/// The place in the sudoers file(s) where a specification was written; the file is only known
+/// after the parsed lines have been processed by `analyze`.
+#[derive(Clone, Default)]
+#[cfg_attr(test, derive(Debug, PartialEq, Eq))]
+pub struct Origin {
+    pub file: Option<std::rc::Rc<std::path::Path>>,
+    pub line: usize,
 }
 
 pub type Defs<T> = Vec<Def<T>>;
@@ -68,14 +92,24 @@ pub struct Def<T>(pub String, pub SpecList<T>);
 /// AST object for directive specifications (aliases, arguments, etc)
 pub enum Directive {
     UserAlias(Defs<UserSpecifier>),
-    HostAlias(Defs<Hostname>),
+    HostAlias(Defs<HostSpecifier>),
     CmndAlias(Defs<Command>),
     RunasAlias(Defs<UserSpecifier>),
-    Defaults(Vec<(String, ConfigValue)>),
+    Defaults(Vec<(String, ConfigValue)>, ConfigScope),
+}
+
+/// The requests to which a `Defaults` directive applies
+pub enum ConfigScope {
+    Generic,
+    Host(SpecList<HostSpecifier>),
+    User(SpecList<UserSpecifier>),
+    RunAs(SpecList<UserSpecifier>),
+    Command(SpecList<Command>),
 }
 
 pub type TextEnum = crate::defaults::StrEnum<'static>;
 
+#[derive(Clone)]
 pub enum ConfigValue {
     Flag(bool),
     Text(Option<Box<str>>),
@@ -84,6 +118,7 @@ pub enum ConfigValue {
     Enum(TextEnum),
 }
 
+#[derive(Clone)]
 pub enum Mode {
     Add,
     Set,
@@ -195,8 +230,8 @@ impl Parse for UserSpecifier {
             // in this case we must fail 'hard', since input has been consumed
             ctor(expect_nonterminal(stream)?)
         } else if accept_if(|c| c == '+', stream).is_ok() {
-            // TODO Netgroups
-            unrecoverable!(stream, "netgroups are not supported yet");
+            let Username(netgroup) = expect_nonterminal(stream)?;
+            UserSpecifier::Netgroup(netgroup)
         } else {
             // in this case we must fail 'softly', since no input has been consumed yet
             UserSpecifier::User(try_nonterminal(stream)?)
@@ -215,6 +250,42 @@ impl Parse for Meta<UserSpecifier> {
     }
 }
 
+/// grammar:
+/// ```text
+/// hostspec = hostname
+///          | +netgroup
+/// ```
+impl Parse for HostSpecifier {
+    fn parse(stream: &mut impl CharStream) -> Parsed<Self> {
+        let hostspec = if accept_if(|c| c == '+', stream).is_ok() {
+            // in this case we must fail 'hard', since input has been consumed
+            let Username(netgroup) = expect_nonterminal(stream)?;
+            HostSpecifier::Netgroup(netgroup)
+        } else {
+            HostSpecifier::Host(try_nonterminal(stream)?)
+        };
+
+        make(hostspec)
+    }
+}
+
+impl Many for HostSpecifier {}
+
+/// HostSpecifier is not a token, implement the parser for `Meta<HostSpecifier>`
+impl Parse for Meta<HostSpecifier> {
+    fn parse(stream: &mut impl CharStream) -> Parsed<Self> {
+        if let Some(meta) = try_nonterminal(stream)? {
+            make(match meta {
+                Meta::All => Meta::All,
+                Meta::Alias(alias) => Meta::Alias(alias),
+                Meta::Only(host) => Meta::Only(HostSpecifier::Host(host)),
+            })
+        } else {
+            make(Meta::Only(HostSpecifier::parse(stream)?))
+        }
+    }
+}
+
 /// grammar:
 /// ```text
 /// runas = "(", userlist, (":", grouplist?)?, ")"
@@ -259,6 +330,8 @@ impl Parse for MetaOrTag {
         let result: Modifier = match keyword.as_str() {
             "PASSWD" => switch(|tag| tag.passwd = true)?,
             "NOPASSWD" => switch(|tag| tag.passwd = false)?,
+            "SETENV" => switch(|tag| tag.setenv = Some(true))?,
+            "NOSETENV" => switch(|tag| tag.setenv = Some(false))?,
             "CWD" => {
                 expect_syntax('=', stream)?;
                 let path: ChDir = expect_nonterminal(stream)?;
@@ -295,14 +368,42 @@ impl Parse for CommandSpec {
         let start_pos = stream.get_pos();
         if let Some(Username(keyword)) = try_nonterminal(stream)? {
             if keyword == "sudoedit" {
-                // note: special behaviour of forward slashes in wildcards, tread carefully
-                unrecoverable!(pos = start_pos, stream, "sudoedit is not yet supported");
-            } else if keyword.starts_with("sha") {
-                unrecoverable!(
-                    pos = start_pos,
-                    stream,
-                    "digest specifications are not supported"
-                )
+                // note: special behaviour of forward slashes in wildcards is handled when matching
+                let mut files = Vec::new();
+                if let Some((file, args)) = maybe(try_nonterminal::<SimpleCommand>(stream))? {
+                    files.push(file.as_str().to_string());
+                    files.extend(args.into_iter().flat_map(Vec::from));
+                }
+                for file in &files {
+                    if !file.starts_with('/') || glob::Pattern::new(file).is_err() {
+                        unrecoverable!(
+                            pos = start_pos,
+                            stream,
+                            "sudoedit needs fully qualified paths, found {file}"
+                        )
+                    }
+                }
+
+                let args = (!files.is_empty()).then(|| files.into_boxed_slice());
+                let sudoedit = SimpleCommand::construct(keyword).expect("internal error");
+                return make(CommandSpec(
+                    tags,
+                    Qualified::Allow(Meta::Only(((sudoedit.0, args), Box::default()))),
+                ));
+            } else if keyword == "list" {
+                // pseudo-command that allows listing the privileges of other users
+                let list = SimpleCommand::construct(keyword).expect("internal error");
+                return make(CommandSpec(
+                    tags,
+                    Qualified::Allow(Meta::Only((list, Box::default()))),
+                ));
+            } else if let Some(algorithm) = DigestAlgorithm::from_name(&keyword) {
+                let digests = parse_digests(algorithm, stream)?;
+                let cmd = expect_nonterminal(stream)?;
+                return make(CommandSpec(
+                    tags,
+                    Qualified::Allow(Meta::Only((cmd, digests))),
+                ));
             } else {
                 unrecoverable!(
                     pos = start_pos,
@@ -318,13 +419,72 @@ impl Parse for CommandSpec {
     }
 }
 
+/// grammar:
+/// ```text
+/// command = digest, ("," digest)*, simplecommand
+///         | simplecommand
+/// digest = ("sha224" | "sha256" | "sha384" | "sha512"), ":", digestvalue
+/// ```
+impl Parse for Meta<Command> {
+    fn parse(stream: &mut impl CharStream) -> Parsed<Self> {
+        let cmd = match try_nonterminal(stream)? {
+            Meta::All => return make(Meta::All),
+            Meta::Alias(alias) => return make(Meta::Alias(alias)),
+            Meta::Only(cmd) => cmd,
+        };
+
+        // the name of a digest algorithm is read as a command, up to the ':' that follows it
+        if let (pattern, None) = &cmd {
+            if let Some(algorithm) = DigestAlgorithm::from_name(pattern.as_str()) {
+                if stream.peek() == Some(':') {
+                    let digests = parse_digests(algorithm, stream)?;
+                    return make(Meta::Only((expect_nonterminal(stream)?, digests)));
+                }
+            }
+        }
+
+        make(Meta::Only((cmd, Box::default())))
+    }
+}
+
+impl Many for Command {}
+
+/// Parse a list of digests, starting after the name of the algorithm of the first digest
+fn parse_digests(
+    mut algorithm: DigestAlgorithm,
+    stream: &mut impl CharStream,
+) -> Parsed<Box<[Digest]>> {
+    let mut digests = Vec::new();
+    loop {
+        expect_syntax(':', stream)?;
+        let start_pos = stream.get_pos();
+        let DigestValue(text) = expect_nonterminal(stream)?;
+        match Digest::decode(algorithm, &text) {
+            Ok(digest) => digests.push(digest),
+            Err(msg) => unrecoverable!(pos = start_pos, stream, "{msg}"),
+        }
+
+        if !is_syntax(',', stream)? {
+            break;
+        }
+        let start_pos = stream.get_pos();
+        let Username(name) = expect_nonterminal(stream)?;
+        let Some(next) = DigestAlgorithm::from_name(&name) else {
+            unrecoverable!(pos = start_pos, stream, "expected digest but found {name}")
+        };
+        algorithm = next;
+    }
+
+    make(digests.into_boxed_slice())
+}
+
 /// Parsing for a tuple of hostname, runas specifier and commandspec.
 /// grammar:
 /// ```text
 /// (host,runas,commandspec) = hostlist, "=", [runas?, commandspec]+
 /// ```
 
-impl Parse for (SpecList<Hostname>, Vec<(Option<RunAs>, CommandSpec)>) {
+impl Parse for (SpecList<HostSpecifier>, Vec<(Option<RunAs>, CommandSpec)>) {
     fn parse(stream: &mut impl CharStream) -> Parsed<Self> {
         let hosts = try_nonterminal(stream)?;
         expect_syntax('=', stream)?;
@@ -336,7 +496,7 @@ impl Parse for (SpecList<Hostname>, Vec<(Option<RunAs>, CommandSpec)>) {
 
 /// A hostname, runas specifier, commandspec combination can occur multiple times in a single
 /// sudoer line (seperated by ":")
-impl Many for (SpecList<Hostname>, Vec<(Option<RunAs>, CommandSpec)>) {
+impl Many for (SpecList<HostSpecifier>, Vec<(Option<RunAs>, CommandSpec)>) {
     const SEP: char = ':';
 }
 
@@ -368,7 +528,7 @@ impl Many for (Option<RunAs>, CommandSpec) {}
 /// ```text
 /// sudo = permissionspec
 ///      | Keyword_Alias identifier = identifier_list
-///      | Defaults (name [+-]?= ...)+
+///      | Defaults scope (name [+-]?= ...)+
 /// ```
 /// There is a syntactical ambiguity in the sudoer Directive and Permission specifications, so we
 /// have to parse them 'together' and do a delayed decision on which category we are in.
@@ -383,6 +543,12 @@ impl Parse for Sudo {
             return parse_include(stream);
         }
 
+        let start_pos = stream.get_pos();
+        let origin = Origin {
+            file: None,
+            line: start_pos.0,
+        };
+
         // the existence of "#include" forces us to handle lines that start with #<ID> explicitly
         if stream.peek() == Some('#') {
             return if let Ok(ident) = try_nonterminal::<Identifier>(stream) {
@@ -397,7 +563,11 @@ impl Parse for Sudo {
                 };
                 // no need to check get_directive as no other directive starts with #
                 let permissions = expect_nonterminal(stream)?;
-                make(Sudo::Spec(PermissionSpec { users, permissions }))
+                make(Sudo::Spec(PermissionSpec {
+                    users,
+                    permissions,
+                    origin,
+                }))
             } else {
                 // the failed "try_nonterminal::<Identifier>" will have consumed the '#'
                 // the most ignominious part of sudoers: having to parse bits of comments
@@ -408,8 +578,35 @@ impl Parse for Sudo {
             };
         }
 
-        let start_pos = stream.get_pos();
-        if let Some(users) = maybe(try_nonterminal::<SpecList<_>>(stream))? {
+        // the scope of a Defaults directive (as in "Defaults:user") directly follows the keyword,
+        // so the first user name needs to be parsed before any whitespace is skipped
+        let users = match maybe(Meta::<Username>::parse(stream))? {
+            Some(Meta::Only(Username(keyword))) if keyword == "Defaults" => {
+                let scope = try_nonterminal(stream)?;
+                return make(Sudo::Decl(Directive::Defaults(
+                    expect_nonterminal(stream)?,
+                    scope,
+                )));
+            }
+            Some(meta) => {
+                skip_trailing_whitespace(stream)?;
+                let first_user = Qualified::Allow(match meta {
+                    Meta::All => Meta::All,
+                    Meta::Alias(alias) => Meta::Alias(alias),
+                    Meta::Only(Username(name)) => {
+                        Meta::Only(UserSpecifier::User(Identifier::Name(name)))
+                    }
+                });
+                let mut users = vec![first_user];
+                if is_syntax(',', stream)? {
+                    users.append(&mut expect_nonterminal(stream)?);
+                }
+                Some(users)
+            }
+            None => maybe(try_nonterminal::<SpecList<_>>(stream))?,
+        };
+
+        if let Some(users) = users {
             // element 1 always exists (parse_list fails on an empty list)
             let key = &users[0];
             if let Some(directive) = maybe(get_directive(key, stream))? {
@@ -419,7 +616,11 @@ impl Parse for Sudo {
                 make(Sudo::Decl(directive))
             } else {
                 let permissions = expect_nonterminal(stream)?;
-                make(Sudo::Spec(PermissionSpec { users, permissions }))
+                make(Sudo::Spec(PermissionSpec {
+                    users,
+                    permissions,
+                    origin,
+                }))
             }
         } else {
             // this will leave whatever could not be parsed on the input stream
@@ -500,11 +701,48 @@ fn get_directive(
         "Host_Alias" => make(HostAlias(expect_nonterminal(stream)?)),
         "Cmnd_Alias" | "Cmd_Alias" => make(CmndAlias(expect_nonterminal(stream)?)),
         "Runas_Alias" => make(RunasAlias(expect_nonterminal(stream)?)),
-        "Defaults" => make(Defaults(expect_nonterminal(stream)?)),
         _ => reject(),
     }
 }
 
+/// grammar:
+/// ```text
+/// scope = [ ":" userlist | "@" hostlist | ">" runaslist | "!" commandlist ]
+/// ```
+/// Commands in the scope of a `Defaults` directive cannot have arguments.
+impl Parse for ConfigScope {
+    fn parse(stream: &mut impl CharStream) -> Parsed<Self> {
+        let scope = if accept_if(|c| c == ':', stream).is_ok() {
+            ConfigScope::User(expect_nonterminal(stream)?)
+        } else if accept_if(|c| c == '@', stream).is_ok() {
+            ConfigScope::Host(expect_nonterminal(stream)?)
+        } else if accept_if(|c| c == '>', stream).is_ok() {
+            ConfigScope::RunAs(expect_nonterminal(stream)?)
+        } else if accept_if(|c| c == '!', stream).is_ok() {
+            let commands: SpecList<DefaultsCommand> = expect_nonterminal(stream)?;
+            ConfigScope::Command(commands.into_iter().map(into_command).collect())
+        } else {
+            ConfigScope::Generic
+        };
+
+        make(scope)
+    }
+}
+
+/// A command in the scope of a `Defaults` directive matches like a command without arguments
+fn into_command(spec: Spec<DefaultsCommand>) -> Spec<Command> {
+    let convert = |meta| match meta {
+        Meta::All => Meta::All,
+        Meta::Alias(alias) => Meta::Alias(alias),
+        Meta::Only(DefaultsCommand(cmd)) => Meta::Only((cmd, Box::default())),
+    };
+
+    match spec {
+        Qualified::Allow(meta) => Qualified::Allow(convert(meta)),
+        Qualified::Forbid(meta) => Qualified::Forbid(convert(meta)),
+    }
+}
+
 /// grammar:
 /// ```text
 /// parameter = name [+-]?= ...
@@ -518,11 +756,11 @@ impl Parse for (String, ConfigValue) {
             if accept_if(|c| c == '"', stream).is_ok() {
                 let mut result = Vec::new();
                 while let Some(EnvVar(name)) = try_nonterminal(stream)? {
-                    result.push(name);
                     if is_syntax('=', stream)? {
-                        let QuotedText(_) = expect_nonterminal(stream)?;
-                        expect_syntax('"', stream)?;
-                        unrecoverable!(stream, "values in environment variables not yet supported")
+                        let EnvValue(value) = expect_nonterminal(stream)?;
+                        result.push(format!("{name}={value}"));
+                    } else {
+                        result.push(name);
                     }
                 }
                 expect_syntax('"', stream)?;
@@ -533,6 +771,13 @@ impl Parse for (String, ConfigValue) {
                 make(result)
             } else {
                 let EnvVar(name) = expect_nonterminal(stream)?;
+                // like the original sudo, a value can only be given in a quoted list
+                if is_syntax('=', stream)? {
+                    unrecoverable!(
+                        stream,
+                        "a value for {name} must be enclosed in double quotes"
+                    );
+                }
 
                 make(vec![name])
             }
@@ -35,17 +35,24 @@ mod names {
         const DESCRIPTION: &'static str = T::DESCRIPTION;
     }
 
-    impl UserFriendly for tokens::Command {
+    impl UserFriendly for tokens::SimpleCommand {
         const DESCRIPTION: &'static str = "path to binary (or sudoedit)";
     }
 
-    impl UserFriendly
-        for (
-            SpecList<tokens::Hostname>,
-            Vec<(Option<RunAs>, CommandSpec)>,
-        )
-    {
-        const DESCRIPTION: &'static str = tokens::Hostname::DESCRIPTION;
+    impl UserFriendly for Command {
+        const DESCRIPTION: &'static str = tokens::SimpleCommand::DESCRIPTION;
+    }
+
+    impl UserFriendly for tokens::DefaultsCommand {
+        const DESCRIPTION: &'static str = tokens::SimpleCommand::DESCRIPTION;
+    }
+
+    impl UserFriendly for tokens::DigestValue {
+        const DESCRIPTION: &'static str = "digest";
+    }
+
+    impl UserFriendly for (SpecList<HostSpecifier>, Vec<(Option<RunAs>, CommandSpec)>) {
+        const DESCRIPTION: &'static str = HostSpecifier::DESCRIPTION;
     }
 
     impl UserFriendly for (Option<RunAs>, CommandSpec) {
@@ -61,10 +68,18 @@ mod names {
         const DESCRIPTION: &'static str = "user";
     }
 
+    impl UserFriendly for tokens::Username {
+        const DESCRIPTION: &'static str = "name";
+    }
+
     impl UserFriendly for tokens::Hostname {
         const DESCRIPTION: &'static str = "host name";
     }
 
+    impl UserFriendly for HostSpecifier {
+        const DESCRIPTION: &'static str = tokens::Hostname::DESCRIPTION;
+    }
+
     impl UserFriendly for tokens::QuotedText {
         const DESCRIPTION: &'static str = "non-empty string";
     }
@@ -85,8 +100,12 @@ mod names {
         const DESCRIPTION: &'static str = "environment variable";
     }
 
+    impl UserFriendly for tokens::EnvValue {
+        const DESCRIPTION: &'static str = "value";
+    }
+
     impl UserFriendly for CommandSpec {
-        const DESCRIPTION: &'static str = tokens::Command::DESCRIPTION;
+        const DESCRIPTION: &'static str = tokens::SimpleCommand::DESCRIPTION;
     }
 
     impl UserFriendly for tokens::ChDir {
@@ -106,3 +125,9 @@ mod names {
 impl<T: super::basic_parser::Parse> UserFriendly for T {
     const DESCRIPTION: &'static str = "elem";
 }
+
+// commands are only parsed as part of `Meta<Command>`, so the blanket implementation misses them
+#[cfg(test)]
+impl UserFriendly for super::ast::Command {
+    const DESCRIPTION: &'static str = "elem";
+}
@@ -156,7 +156,8 @@ impl Parse for Comment {
     }
 }
 
-fn skip_trailing_whitespace(stream: &mut impl CharStream) -> Parsed<()> {
+/// Skip whitespace (and comments) after an item that was parsed without [try_nonterminal].
+pub fn skip_trailing_whitespace(stream: &mut impl CharStream) -> Parsed<()> {
     TrailingWhitespace::parse(stream)?;
     make(())
 }
@@ -0,0 +1,289 @@
+//! Commands in the sudoers file can be restricted to executables with a known SHA-2 digest:
+//! ```text
+//! user ALL = sha256:76ef9e2e7bcdf7bb... /usr/bin/backup
+//! ```
+//! The digests themselves are computed by the `sha2` crate.
+
+use std::cell::{OnceCell, RefCell};
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::Path;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DigestAlgorithm {
+    Sha224,
+    Sha256,
+    Sha384,
+    Sha512,
+}
+
+impl DigestAlgorithm {
+    pub fn from_name(name: &str) -> Option<DigestAlgorithm> {
+        match name {
+            "sha224" => Some(DigestAlgorithm::Sha224),
+            "sha256" => Some(DigestAlgorithm::Sha256),
+            "sha384" => Some(DigestAlgorithm::Sha384),
+            "sha512" => Some(DigestAlgorithm::Sha512),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            DigestAlgorithm::Sha224 => "sha224",
+            DigestAlgorithm::Sha256 => "sha256",
+            DigestAlgorithm::Sha384 => "sha384",
+            DigestAlgorithm::Sha512 => "sha512",
+        }
+    }
+
+    /// The size of a digest in bytes
+    fn len(self) -> usize {
+        match self {
+            DigestAlgorithm::Sha224 => 28,
+            DigestAlgorithm::Sha256 => 32,
+            DigestAlgorithm::Sha384 => 48,
+            DigestAlgorithm::Sha512 => 64,
+        }
+    }
+
+    /// Compute the digest of everything that can be read from `input`
+    pub fn hash(self, input: impl Read) -> io::Result<Box<[u8]>> {
+        match self {
+            DigestAlgorithm::Sha224 => run::<sha2::Sha224>(input),
+            DigestAlgorithm::Sha256 => run::<sha2::Sha256>(input),
+            DigestAlgorithm::Sha384 => run::<sha2::Sha384>(input),
+            DigestAlgorithm::Sha512 => run::<sha2::Sha512>(input),
+        }
+    }
+}
+
+/// A digest that an executable should have
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Digest {
+    pub algorithm: DigestAlgorithm,
+    pub value: Box<[u8]>,
+}
+
+impl Digest {
+    /// Decode a digest that is written in hexadecimal or base64 notation, like the original sudo
+    /// accepts.
+    pub fn decode(algorithm: DigestAlgorithm, text: &str) -> Result<Digest, String> {
+        let value = if text.len() == 2 * algorithm.len() {
+            decode_hex(text)
+        } else {
+            decode_base64(text)
+        };
+
+        match value {
+            Some(value) if value.len() == algorithm.len() => Ok(Digest {
+                algorithm,
+                value: value.into_boxed_slice(),
+            }),
+            _ => Err(format!("invalid {} digest: {text}", algorithm.name())),
+        }
+    }
+}
+
+fn decode_hex(text: &str) -> Option<Vec<u8>> {
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(text.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+fn decode_base64(text: &str) -> Option<Vec<u8>> {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    // padding is optional
+    let text = text.trim_end_matches('=');
+    let mut bits = 0u32;
+    let mut count = 0;
+    let mut result = Vec::new();
+    for c in text.bytes() {
+        bits = bits << 6 | ALPHABET.iter().position(|&x| x == c)? as u32;
+        count += 6;
+        if count >= 8 {
+            count -= 8;
+            result.push((bits >> count) as u8);
+        }
+    }
+
+    Some(result)
+}
+
+/// The executable that a request is for. It is only opened when a digest has to be checked;
+/// the same open file can then be executed, so that it cannot be replaced after the check.
+pub struct Executable<'a> {
+    path: &'a Path,
+    file: OnceCell<Option<File>>,
+    digests: RefCell<HashMap<DigestAlgorithm, Option<Box<[u8]>>>>,
+}
+
+impl<'a> Executable<'a> {
+    pub fn new(path: &'a Path) -> Self {
+        Executable {
+            path,
+            file: OnceCell::new(),
+            digests: Default::default(),
+        }
+    }
+
+    pub fn path(&self) -> &'a Path {
+        self.path
+    }
+
+    /// Check whether the executable has the given digest; an executable that cannot be read
+    /// never matches.
+    pub fn has_digest(&self, digest: &Digest) -> bool {
+        let mut known = self.digests.borrow_mut();
+        let actual = known.entry(digest.algorithm).or_insert_with(|| {
+            let mut file = self
+                .file
+                .get_or_init(|| open_regular_file(self.path).ok())
+                .as_ref()?;
+            file.seek(SeekFrom::Start(0)).ok()?;
+            digest.algorithm.hash(file).ok()
+        });
+
+        actual.as_deref() == Some(&digest.value)
+    }
+
+    /// The file that was opened to check a digest, if that happened
+    pub fn into_file(self) -> Option<File> {
+        self.file.into_inner().flatten()
+    }
+}
+
+/// Open the file at `path` for reading, if it is a regular file; opening does not block, so that
+/// e.g. a FIFO is not waited on, and devices are never read.
+fn open_regular_file(path: &Path) -> io::Result<File> {
+    let file = OpenOptions::new()
+        .read(true)
+        .custom_flags(libc::O_NONBLOCK)
+        .open(path)?;
+
+    if file.metadata()?.is_file() {
+        Ok(file)
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} is not a regular file", path.display()),
+        ))
+    }
+}
+
+fn run<D: sha2::Digest + io::Write>(mut input: impl Read) -> io::Result<Box<[u8]>> {
+    let mut hasher = D::new();
+    io::copy(&mut input, &mut hasher)?;
+
+    Ok(hasher.finalize().to_vec().into_boxed_slice())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn hex(algorithm: DigestAlgorithm, input: &[u8]) -> String {
+        let digest = algorithm.hash(input).unwrap();
+        digest.iter().map(|byte| format!("{byte:02x}")).collect()
+    }
+
+    #[test]
+    fn sha2_test_vectors() {
+        use DigestAlgorithm::*;
+
+        assert_eq!(
+            hex(Sha224, b"abc"),
+            "23097d223405d8228642a477bda255b32aadbce4bda0b3f7e36c9da7"
+        );
+        assert_eq!(
+            hex(Sha256, b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(
+            hex(Sha256, b""),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(
+            hex(
+                Sha256,
+                b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq"
+            ),
+            "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1"
+        );
+        assert_eq!(
+            hex(Sha384, b"abc"),
+            "cb00753f45a35e8bb5a03d699ac65007272c32ab0eded1631a8b605a43ff5bed\
+             8086072ba1e7cc2358baeca134c825a7"
+        );
+        assert_eq!(
+            hex(Sha512, b"abc"),
+            "ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a\
+             2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f"
+        );
+        assert_eq!(
+            hex(
+                Sha512,
+                b"abcdefghbcdefghicdefghijdefghijkefghijklfghijklmghijklmn\
+                  hijklmnoijklmnopjklmnopqklmnopqrlmnopqrsmnopqrstnopqrstu"
+            ),
+            "8e959b75dae313da8cf4f72814fc143f8f7779c6eb9f7fa17299aeadb6889018\
+             501d289e4900f7e4331b99dec4b5433ac7d329eeb6dd26545e96e55b874be909"
+        );
+        assert_eq!(
+            hex(Sha256, &vec![b'a'; 1_000_000]),
+            "cdc76e5c9914fb9281a1c7e284d73e67f1809a48a497200e046d39ccc7112cd0"
+        );
+    }
+
+    #[test]
+    fn decode_digests() {
+        let abc = DigestAlgorithm::Sha224.hash(&b"abc"[..]).unwrap();
+        let hex = Digest::decode(
+            DigestAlgorithm::Sha224,
+            "23097d223405d8228642a477bda255b32aadbce4bda0b3f7e36c9da7",
+        )
+        .unwrap();
+        let base64 = Digest::decode(
+            DigestAlgorithm::Sha224,
+            "Iwl9IjQF2CKGQqR3vaJVsyqtvOS9oLP342ydpw==",
+        )
+        .unwrap();
+        assert_eq!(hex.value, abc);
+        assert_eq!(base64.value, abc);
+        assert_eq!(
+            Digest::decode(
+                DigestAlgorithm::Sha224,
+                "Iwl9IjQF2CKGQqR3vaJVsyqtvOS9oLP342ydpw"
+            )
+            .unwrap(),
+            hex
+        );
+
+        assert!(Digest::decode(DigestAlgorithm::Sha256, "23097d22").is_err());
+        assert!(Digest::decode(
+            DigestAlgorithm::Sha224,
+            "23097d223405d8228642a477bda255b32aadbce4bda0b3f7e36c9dzz"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn only_regular_files_are_hashed() {
+        let digest = Digest {
+            algorithm: DigestAlgorithm::Sha256,
+            value: DigestAlgorithm::Sha256.hash(&b""[..]).unwrap(),
+        };
+
+        // a device that never ends is not read at all
+        let device = Executable::new(Path::new("/dev/zero"));
+        assert!(!device.has_digest(&digest));
+        assert!(device.into_file().is_none());
+
+        let directory = Executable::new(Path::new("/"));
+        assert!(!directory.has_digest(&digest));
+    }
+}
@@ -0,0 +1,248 @@
+//! Representation of the permissions of a user, as displayed by `sudo -l`
+
+use std::fmt;
+
+use super::ast::*;
+use super::tokens::*;
+
+/// A group of command specifications that share the same RunAs specification; this corresponds
+/// to a single line in the output of `sudo -l`. The alternate format (`{:#}`) produces the
+/// longer output of `sudo -ll`.
+pub struct Entry<'a> {
+    runas: Option<&'a RunAs>,
+    cmd_specs: Vec<(Tag, &'a Spec<Command>)>,
+    origin: &'a Origin,
+}
+
+impl<'a> Entry<'a> {
+    pub(super) fn new(
+        runas: Option<&'a RunAs>,
+        cmd_specs: Vec<(Tag, &'a Spec<Command>)>,
+        origin: &'a Origin,
+    ) -> Self {
+        Entry {
+            runas,
+            cmd_specs,
+            origin,
+        }
+    }
+
+    pub(super) fn has_runas(&self, runas: Option<&RunAs>) -> bool {
+        match (self.runas, runas) {
+            (Some(x), Some(y)) => std::ptr::eq(x, y),
+            (None, None) => true,
+            _ => false,
+        }
+    }
+
+    pub(super) fn push(&mut self, cmd_spec: (Tag, &'a Spec<Command>)) {
+        self.cmd_specs.push(cmd_spec)
+    }
+
+    /// The tags that apply to each of the commands in this entry
+    pub(super) fn tags(&self) -> impl Iterator<Item = &Tag> {
+        self.cmd_specs.iter().map(|(tag, _)| tag)
+    }
+}
+
+impl fmt::Display for Entry<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            return self.fmt_long(f);
+        }
+
+        match self.runas {
+            Some(RunAs { users, groups }) => {
+                write!(f, "(")?;
+                write_list(f, users)?;
+                if !groups.is_empty() {
+                    write!(f, " : ")?;
+                    write_list(f, groups)?;
+                }
+                write!(f, ")")?;
+            }
+            None => write!(f, "(root)")?,
+        }
+
+        // tags are only displayed when they differ from the previous command
+        let mut last_tag = Tag::default();
+        for (i, (tag, spec)) in self.cmd_specs.iter().enumerate() {
+            write!(f, "{}", if i == 0 { " " } else { ", " })?;
+            if tag.passwd != last_tag.passwd {
+                write!(f, "{}", if tag.passwd { "PASSWD: " } else { "NOPASSWD: " })?;
+            }
+            if tag.setenv != last_tag.setenv {
+                match tag.setenv {
+                    Some(true) => write!(f, "SETENV: ")?,
+                    Some(false) => write!(f, "NOSETENV: ")?,
+                    None => {}
+                }
+            }
+            if tag.cwd != last_tag.cwd {
+                match &tag.cwd {
+                    Some(ChDir::Path(path)) => write!(f, "CWD={} ", path.display())?,
+                    Some(ChDir::Any) => write!(f, "CWD=* ")?,
+                    None => {}
+                }
+            }
+            spec.write_syntax(f)?;
+            last_tag = tag.clone();
+        }
+
+        Ok(())
+    }
+}
+
+impl Entry<'_> {
+    /// Write one block for every run of commands that have the same tags
+    fn fmt_long(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut last_tag = None;
+        for (tag, spec) in &self.cmd_specs {
+            if last_tag != Some(tag) {
+                if last_tag.is_some() {
+                    write!(f, "\n\n")?;
+                }
+                self.write_block_header(f, tag)?;
+                last_tag = Some(tag);
+            }
+            write!(f, "\n\t")?;
+            spec.write_syntax(f)?;
+        }
+
+        Ok(())
+    }
+
+    fn write_block_header(&self, f: &mut fmt::Formatter<'_>, tag: &Tag) -> fmt::Result {
+        write!(f, "Sudoers entry:")?;
+        if let Some(file) = &self.origin.file {
+            write!(f, " {}:{}", file.display(), self.origin.line)?;
+        }
+        writeln!(f)?;
+
+        match self.runas {
+            Some(RunAs { users, groups }) => {
+                if !users.is_empty() || groups.is_empty() {
+                    write!(f, "    RunAsUsers: ")?;
+                    write_list(f, users)?;
+                    writeln!(f)?;
+                }
+                if !groups.is_empty() {
+                    write!(f, "    RunAsGroups: ")?;
+                    write_list(f, groups)?;
+                    writeln!(f)?;
+                }
+            }
+            None => writeln!(f, "    RunAsUsers: root")?,
+        }
+
+        let mut options = Vec::new();
+        if !tag.passwd {
+            options.push("!authenticate");
+        }
+        match tag.setenv {
+            Some(true) => options.push("setenv"),
+            Some(false) => options.push("!setenv"),
+            None => {}
+        }
+        if !options.is_empty() {
+            writeln!(f, "    Options: {}", options.join(", "))?;
+        }
+        match &tag.cwd {
+            Some(ChDir::Path(path)) => writeln!(f, "    Cwd: {}", path.display())?,
+            Some(ChDir::Any) => writeln!(f, "    Cwd: *")?,
+            None => {}
+        }
+
+        write!(f, "    Commands:")
+    }
+}
+
+fn write_list<T: WriteSyntax>(f: &mut fmt::Formatter<'_>, items: &[T]) -> fmt::Result {
+    for (i, item) in items.iter().enumerate() {
+        if i > 0 {
+            write!(f, ", ")?;
+        }
+        item.write_syntax(f)?;
+    }
+
+    Ok(())
+}
+
+/// Display AST items in the syntax they were written in
+trait WriteSyntax {
+    fn write_syntax(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result;
+}
+
+impl<T: WriteSyntax> WriteSyntax for Qualified<T> {
+    fn write_syntax(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Qualified::Allow(item) => item.write_syntax(f),
+            Qualified::Forbid(item) => {
+                write!(f, "!")?;
+                item.write_syntax(f)
+            }
+        }
+    }
+}
+
+impl<T: WriteSyntax> WriteSyntax for Meta<T> {
+    fn write_syntax(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Meta::All => write!(f, "ALL"),
+            Meta::Alias(name) => write!(f, "{name}"),
+            Meta::Only(item) => item.write_syntax(f),
+        }
+    }
+}
+
+impl WriteSyntax for Identifier {
+    fn write_syntax(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Identifier::Name(name) => write!(f, "{name}"),
+            Identifier::ID(num) => write!(f, "#{num}"),
+        }
+    }
+}
+
+impl WriteSyntax for UserSpecifier {
+    fn write_syntax(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UserSpecifier::User(id) => id.write_syntax(f),
+            UserSpecifier::Group(id) => {
+                write!(f, "%")?;
+                id.write_syntax(f)
+            }
+            UserSpecifier::NonunixGroup(id) => {
+                write!(f, "%:")?;
+                id.write_syntax(f)
+            }
+            UserSpecifier::Netgroup(name) => write!(f, "+{name}"),
+        }
+    }
+}
+
+impl WriteSyntax for Command {
+    fn write_syntax(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ((cmd, args), digests) = self;
+        for (i, digest) in digests.iter().enumerate() {
+            let separator = if i + 1 < digests.len() { "," } else { " " };
+            write!(f, "{}:", digest.algorithm.name())?;
+            for byte in digest.value.iter() {
+                write!(f, "{byte:02x}")?;
+            }
+            write!(f, "{separator}")?;
+        }
+        write!(f, "{}", cmd.as_str())?;
+        match args.as_deref() {
+            None => {}
+            Some([]) => write!(f, " \"\"")?,
+            Some(args) => {
+                for arg in args {
+                    write!(f, " {arg}")?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
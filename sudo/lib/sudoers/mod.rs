@@ -1,19 +1,26 @@
 #![forbid(unsafe_code)]
 
-//! Code that checks (and in the future: lists) permissions in the sudoers file
+//! Code that checks and lists permissions in the sudoers file
 
 mod ast;
 mod ast_names;
 mod basic_parser;
 mod char_stream;
+mod digest;
+mod entry;
 mod tokens;
 
 use std::collections::{HashMap, HashSet};
-use std::path::Path;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
 
 use crate::log::auth_warn;
+use crate::system::group_plugin::{GroupFile, GroupProvider};
 use crate::system::interface::{UnixGroup, UnixUser};
+use crate::system::netgroup::{NetgroupProvider, SystemNetgroups};
 use ast::*;
+use digest::Executable;
 use tokens::*;
 
 /// How many nested include files do we allow?
@@ -21,13 +28,35 @@ const INCLUDE_LIMIT: u8 = 128;
 
 /// Export some necessary symbols from modules
 pub use ast::TextEnum;
-pub struct Error(pub Option<basic_parser::Position>, pub String);
+pub use entry::Entry;
+/// A problem found while processing a sudoers file; `source` is the file in which it was found
+/// (if that is known), and `location` the position of the offending text in that file.
+pub struct Error {
+    pub source: Option<PathBuf>,
+    pub location: Option<basic_parser::Position>,
+    pub message: String,
+}
 
-#[derive(Default)]
 pub struct Sudoers {
     rules: Vec<PermissionSpec>,
     aliases: AliasTable,
     settings: Settings,
+    scoped_settings: Vec<(ConfigScope, Vec<(String, ConfigValue)>)>,
+    netgroups: Box<dyn NetgroupProvider>,
+    group_plugin: Option<Box<dyn GroupProvider>>,
+}
+
+impl Default for Sudoers {
+    fn default() -> Self {
+        Sudoers {
+            rules: Default::default(),
+            aliases: Default::default(),
+            settings: Default::default(),
+            scoped_settings: Default::default(),
+            netgroups: Box::new(SystemNetgroups),
+            group_plugin: None,
+        }
+    }
 }
 
 /// A structure that represents what the user wants to do
@@ -42,17 +71,32 @@ pub struct Request<'a, User: UnixUser, Group: UnixGroup> {
 pub struct Judgement {
     flags: Option<Tag>,
     settings: Settings,
+    command_file: Option<File>,
 }
 
 mod policy;
 
-pub use policy::{Authorization, DirChange, Policy, PreJudgementPolicy};
+pub use policy::{
+    AuthenticatingUser, Authentication, Authorization, DirChange, Lecture, Policy,
+    PreJudgementPolicy, TimestampType,
+};
 
 /// This function takes a file argument for a sudoers file and processes it.
 impl Sudoers {
     pub fn new(path: impl AsRef<Path>) -> Result<(Sudoers, Vec<Error>), std::io::Error> {
         let sudoers = read_sudoers(path.as_ref())?;
-        Ok(analyze(sudoers))
+        Ok(analyze(path.as_ref(), sudoers))
+    }
+
+    /// Use `provider` to look up the members of netgroups, instead of the C library
+    pub fn set_netgroup_provider(&mut self, provider: impl NetgroupProvider + 'static) {
+        self.netgroups = Box::new(provider);
+    }
+
+    /// Use `provider` to look up the members of non-Unix groups, instead of the provider that is
+    /// configured with the `group_plugin` setting
+    pub fn set_group_provider(&mut self, provider: impl GroupProvider + 'static) {
+        self.group_plugin = Some(Box::new(provider));
     }
 
     pub fn check<User: UnixUser + PartialEq<User>, Group: UnixGroup>(
@@ -65,7 +109,14 @@ impl Sudoers {
         let skip_passwd =
             am_user.is_root() || (request.user == am_user && in_group(am_user, request.group));
 
-        let mut flags = check_permission(self, am_user, on_host, request);
+        let executable = Executable::new(request.command);
+        let settings = settings_for(
+            self,
+            am_user,
+            on_host,
+            Some((request.user, &executable, request.arguments)),
+        );
+        let mut flags = check_permission(self, am_user, on_host, &executable, request);
         if let Some(Tag { passwd, .. }) = flags.as_mut() {
             if skip_passwd {
                 *passwd = false
@@ -73,9 +124,175 @@ impl Sudoers {
         }
 
         Judgement {
+            command_file: flags.as_ref().and_then(|_| executable.into_file()),
             flags,
-            settings: self.settings.clone(), // this is wasteful, but in the future this will not be a simple clone and it avoids a lifetime
+            settings,
+        }
+    }
+
+    /// Find all the permissions that `am_user` has on machine `on_host`, as displayed by `sudo -l`
+    pub fn matching_entries<'a>(
+        &'a self,
+        am_user: &impl UnixUser,
+        on_host: &str,
+    ) -> Vec<Entry<'a>> {
+        matching_entries(self, am_user, on_host)
+    }
+
+    /// Find aliases that are referenced by a permission but not defined, and aliases that are
+    /// defined but never referenced. These do not affect how the policy is applied, but are
+    /// likely mistakes; `visudo -s` treats them as errors.
+    pub fn alias_warnings(&self) -> Vec<Error> {
+        let mut used_in_rules: [Vec<&String>; 4] = Default::default();
+        let [user, host, cmnd, runas] = &mut used_in_rules;
+        for rule in &self.rules {
+            user.extend(alias_references(&rule.users));
+            for (hosts, cmnd_specs) in &rule.permissions {
+                host.extend(alias_references(hosts));
+                for (runas_spec, CommandSpec(_, command)) in cmnd_specs {
+                    if let Some(RunAs { users, groups }) = runas_spec {
+                        runas.extend(alias_references(users));
+                        runas.extend(alias_references(groups));
+                    }
+                    cmnd.extend(alias_references(std::slice::from_ref(command)));
+                }
+            }
+        }
+        for (scope, _) in &self.scoped_settings {
+            match scope {
+                ConfigScope::Generic => {}
+                ConfigScope::Host(hosts) => host.extend(alias_references(hosts)),
+                ConfigScope::User(users) => user.extend(alias_references(users)),
+                ConfigScope::RunAs(users) => runas.extend(alias_references(users)),
+                ConfigScope::Command(commands) => cmnd.extend(alias_references(commands)),
+            }
+        }
+
+        let aliases = &self.aliases;
+        let definitions: [Vec<(&String, Vec<&String>)>; 4] = [
+            alias_definitions(&aliases.user.1),
+            alias_definitions(&aliases.host.1),
+            alias_definitions(&aliases.cmnd.1),
+            alias_definitions(&aliases.runas.1),
+        ];
+        let kinds = ["User_Alias", "Host_Alias", "Cmnd_Alias", "Runas_Alias"];
+
+        let mut warnings = Vec::new();
+        let mut warn = |message| {
+            warnings.push(Error {
+                source: None,
+                location: None,
+                message,
+            })
+        };
+
+        for ((kind, used_in_rules), definitions) in
+            kinds.iter().zip(&used_in_rules).zip(&definitions)
+        {
+            let defined = definitions
+                .iter()
+                .map(|(name, _)| *name)
+                .collect::<HashSet<_>>();
+            let used = used_in_rules
+                .iter()
+                .copied()
+                .chain(
+                    definitions
+                        .iter()
+                        .flat_map(|(_, refs)| refs.iter().copied()),
+                )
+                .collect::<HashSet<_>>();
+
+            // undefined aliases in alias definitions are already reported by `analyze`
+            let mut reported = HashSet::new();
+            for name in used_in_rules {
+                if !defined.contains(name) && reported.insert(name) {
+                    warn(format!("{kind} '{name}' referenced but not defined"));
+                }
+            }
+            for (name, _) in definitions {
+                if !used.contains(name) {
+                    warn(format!("unused {kind} '{name}'"));
+                }
+            }
+        }
+
+        warnings
+    }
+
+    /// Check whether `am_user` is allowed to list the permissions of `list_user` on machine
+    /// `on_host`. Users can always list their own permissions (but whether this requires
+    /// authentication is governed by the `listpw` setting); for other users this requires being
+    /// root or being allowed to run the `list` pseudo-command.
+    pub fn check_list_permission<User: UnixUser + PartialEq<User>>(
+        &self,
+        am_user: &User,
+        on_host: &str,
+        list_user: &User,
+    ) -> Authorization {
+        if am_user.is_root() {
+            return Authorization::Allowed {
+                must_authenticate: false,
+            };
+        } else if am_user != list_user {
+            return match check_pseudo_command(self, am_user, on_host, "list") {
+                Some(Tag { passwd, .. }) => Authorization::Allowed {
+                    must_authenticate: passwd,
+                },
+                None => Authorization::Forbidden,
+            };
+        }
+
+        self.check_entries_authorization(am_user, on_host, "listpw")
+    }
+
+    /// Check whether `am_user` is allowed to validate their credentials on machine `on_host`
+    /// (i.e. `sudo -v`); whether this requires authentication is governed by `verifypw`.
+    pub fn check_validate_permission(
+        &self,
+        am_user: &impl UnixUser,
+        on_host: &str,
+    ) -> Authorization {
+        if am_user.is_root() {
+            return Authorization::Allowed {
+                must_authenticate: false,
+            };
+        }
+
+        self.check_entries_authorization(am_user, on_host, "verifypw")
+    }
+
+    /// Determine how `am_user` is authenticated on machine `on_host` for an action that does not
+    /// involve a specific command, such as listing or validating.
+    pub fn authentication(&self, am_user: &impl UnixUser, on_host: &str) -> Authentication {
+        Authentication::from(&settings_for(self, am_user, on_host, None))
+    }
+
+    /// Determine the authorization for an action that concerns all the entries that apply to a
+    /// user, based on the NOPASSWD tags of those entries and the value of `setting`, which can be
+    /// "all", "always", "any" or "never".
+    fn check_entries_authorization(
+        &self,
+        am_user: &impl UnixUser,
+        on_host: &str,
+        setting: &str,
+    ) -> Authorization {
+        let entries = self.matching_entries(am_user, on_host);
+        if entries.is_empty() {
+            return Authorization::Forbidden;
         }
+
+        let settings = settings_for(self, am_user, on_host, None);
+        let mut tags = entries.iter().flat_map(|entry| entry.tags());
+        let must_authenticate = match &*settings.enum_value[setting] {
+            "never" => false,
+            "always" => true,
+            // "any": a single NOPASSWD entry suffices; "all": every entry needs NOPASSWD
+            "any" => tags.all(|tag| tag.passwd),
+            _ => tags.any(|tag| tag.passwd),
+        };
+
+        Authorization::Allowed { must_authenticate }
     }
 }
 
@@ -95,7 +312,7 @@ fn read_sudoers(path: &Path) -> Result<Vec<basic_parser::Parsed<Sudo>>, std::io:
 #[derive(Default)]
 pub(super) struct AliasTable {
     user: VecOrd<Def<UserSpecifier>>,
-    host: VecOrd<Def<Hostname>>,
+    host: VecOrd<Def<HostSpecifier>>,
     cmnd: VecOrd<Def<Command>>,
     runas: VecOrd<Def<UserSpecifier>>,
 }
@@ -116,28 +333,33 @@ fn elems<T>(vec: &VecOrd<T>) -> impl Iterator<Item = &T> {
 // This code is structure to allow easily reading the 'happy path'; i.e. as soon as something
 // doesn't match, we escape using the '?' mechanism.
 fn check_permission<User: UnixUser + PartialEq<User>, Group: UnixGroup>(
-    Sudoers { rules, aliases, .. }: &Sudoers,
+    sudoers: &Sudoers,
     am_user: &User,
     on_host: &str,
+    executable: &Executable,
     request: Request<User, Group>,
 ) -> Option<Tag> {
-    let cmdline = (request.command, request.arguments);
-
-    let user_aliases = get_aliases(&aliases.user, &match_user(am_user));
-    let host_aliases = get_aliases(&aliases.host, &match_token(on_host));
-    let cmnd_aliases = get_aliases(&aliases.cmnd, &match_command(cmdline));
-    let runas_user_aliases = get_aliases(&aliases.runas, &match_user(request.user));
+    let Sudoers {
+        rules,
+        aliases,
+        netgroups,
+        ..
+    } = sudoers;
+    let user_aliases = get_aliases(&aliases.user, &match_user(am_user, sudoers));
+    let host_aliases = get_aliases(&aliases.host, &match_host(on_host, &**netgroups));
+    let cmnd_aliases = get_aliases(&aliases.cmnd, &match_command(executable, request.arguments));
+    let runas_user_aliases = get_aliases(&aliases.runas, &match_user(request.user, sudoers));
     let runas_group_aliases = get_aliases(&aliases.runas, &match_group_alias(request.group));
 
     let allowed_commands = rules
         .iter()
         .filter_map(|sudo| {
-            find_item(&sudo.users, &match_user(am_user), &user_aliases)?;
+            find_item(&sudo.users, &match_user(am_user, sudoers), &user_aliases)?;
             Some(&sudo.permissions)
         })
         .flatten()
         .filter_map(|(hosts, runas_cmds)| {
-            find_item(hosts, &match_token(on_host), &host_aliases)?;
+            find_item(hosts, &match_host(on_host, &**netgroups), &host_aliases)?;
             // TODO: calling collect() here is not necessary if find_item is a forward loop
             Some(distribute_tags(runas_cmds).collect::<Vec<_>>())
         })
@@ -145,7 +367,11 @@ fn check_permission<User: UnixUser + PartialEq<User>, Group: UnixGroup>(
         .filter_map(|(runas, cmdspec)| {
             if let Some(RunAs { users, groups }) = runas {
                 if !users.is_empty() || request.user != am_user {
-                    find_item(users, &match_user(request.user), &runas_user_aliases)?
+                    find_item(
+                        users,
+                        &match_user(request.user, sudoers),
+                        &runas_user_aliases,
+                    )?
                 }
                 if !in_group(request.user, request.group) {
                     find_item(groups, &match_group(request.group), &runas_group_aliases)?
@@ -154,10 +380,159 @@ fn check_permission<User: UnixUser + PartialEq<User>, Group: UnixGroup>(
                 None?;
             }
 
-            Some(cmdspec)
+            // like in the original sudo, SETENV is implied for ALL unless NOSETENV is specified
+            let (mut tag, command) = cmdspec;
+            if let Qualified::Allow(Meta::All) = command {
+                tag.setenv.get_or_insert(true);
+            }
+
+            Some((tag, command))
         });
 
-    find_item(allowed_commands, &match_command(cmdline), &cmnd_aliases)
+    find_item(
+        allowed_commands,
+        &match_command(executable, request.arguments),
+        &cmnd_aliases,
+    )
+}
+
+/// Determine the settings that apply to `am_user` on `on_host` (running a command as another user,
+/// if that is specified): the generic `Defaults`, followed by the `Defaults` for the host, the
+/// user, the runas user and the command (in that order) that match.
+fn settings_for<User: UnixUser>(
+    sudoers: &Sudoers,
+    am_user: &User,
+    on_host: &str,
+    command: Option<(&User, &Executable, &[String])>,
+) -> Settings {
+    let Sudoers {
+        aliases,
+        settings,
+        scoped_settings,
+        netgroups,
+        ..
+    } = sudoers;
+    let mut settings = settings.clone();
+    if scoped_settings.is_empty() {
+        return settings;
+    }
+
+    let user_aliases = get_aliases(&aliases.user, &match_user(am_user, sudoers));
+    let host_aliases = get_aliases(&aliases.host, &match_host(on_host, &**netgroups));
+    let (runas_user_aliases, cmnd_aliases) = match command {
+        Some((runas_user, executable, arguments)) => (
+            get_aliases(&aliases.runas, &match_user(runas_user, sudoers)),
+            get_aliases(&aliases.cmnd, &match_command(executable, arguments)),
+        ),
+        None => Default::default(),
+    };
+
+    let applies = |scope: &ConfigScope| match (scope, command) {
+        (ConfigScope::Generic, _) => false,
+        (ConfigScope::Host(hosts), _) => {
+            find_item(hosts, &match_host(on_host, &**netgroups), &host_aliases).is_some()
+        }
+        (ConfigScope::User(users), _) => {
+            find_item(users, &match_user(am_user, sudoers), &user_aliases).is_some()
+        }
+        (ConfigScope::RunAs(users), Some((runas_user, _, _))) => {
+            find_item(users, &match_user(runas_user, sudoers), &runas_user_aliases).is_some()
+        }
+        (ConfigScope::Command(commands), Some((_, executable, arguments))) => find_item(
+            commands,
+            &match_command(executable, arguments),
+            &cmnd_aliases,
+        )
+        .is_some(),
+        (ConfigScope::RunAs(_) | ConfigScope::Command(_), None) => false,
+    };
+
+    // `analyze` has ordered these by the kind of scope
+    for (_, params) in scoped_settings.iter().filter(|(scope, _)| applies(scope)) {
+        for (name, value) in params {
+            settings.set(name.clone(), value.clone());
+        }
+    }
+
+    settings
+}
+
+/// Check if the user `am_user` is allowed to run a pseudo-command such as 'list' on machine
+/// `on_host`; these are not executed, so any RunAs specification is irrelevant.
+fn check_pseudo_command(
+    sudoers: &Sudoers,
+    am_user: &impl UnixUser,
+    on_host: &str,
+    command: &str,
+) -> Option<Tag> {
+    let Sudoers {
+        rules,
+        aliases,
+        netgroups,
+        ..
+    } = sudoers;
+    let executable = Executable::new(Path::new(command));
+    let matches_command = match_command(&executable, &[]);
+
+    let user_aliases = get_aliases(&aliases.user, &match_user(am_user, sudoers));
+    let host_aliases = get_aliases(&aliases.host, &match_host(on_host, &**netgroups));
+    let cmnd_aliases = get_aliases(&aliases.cmnd, &matches_command);
+
+    let allowed_commands = rules
+        .iter()
+        .filter_map(|sudo| {
+            find_item(&sudo.users, &match_user(am_user, sudoers), &user_aliases)?;
+            Some(&sudo.permissions)
+        })
+        .flatten()
+        .filter_map(|(hosts, runas_cmds)| {
+            find_item(hosts, &match_host(on_host, &**netgroups), &host_aliases)?;
+            Some(distribute_tags(runas_cmds).collect::<Vec<_>>())
+        })
+        .flatten()
+        .map(|(_, cmdspec)| cmdspec);
+
+    find_item(allowed_commands, &matches_command, &cmnd_aliases)
+}
+
+/// Collect the command specifications that apply to `am_user` on `on_host`, grouped by their
+/// RunAs specification; this is the same user/host filtering that [check_permission] performs.
+fn matching_entries<'a>(
+    sudoers: &'a Sudoers,
+    am_user: &impl UnixUser,
+    on_host: &str,
+) -> Vec<Entry<'a>> {
+    let Sudoers {
+        rules,
+        aliases,
+        netgroups,
+        ..
+    } = sudoers;
+    let user_aliases = get_aliases(&aliases.user, &match_user(am_user, sudoers));
+    let host_aliases = get_aliases(&aliases.host, &match_host(on_host, &**netgroups));
+
+    let mut entries: Vec<Entry> = Vec::new();
+    for (origin, (_, runas_cmds)) in rules
+        .iter()
+        .filter(|sudo| {
+            find_item(&sudo.users, &match_user(am_user, sudoers), &user_aliases).is_some()
+        })
+        .flat_map(|sudo| sudo.permissions.iter().map(|perm| (&sudo.origin, perm)))
+        .filter(|(_, (hosts, _))| {
+            find_item(hosts, &match_host(on_host, &**netgroups), &host_aliases).is_some()
+        })
+    {
+        let first = entries.len();
+        for (runas, cmd_spec) in distribute_tags(runas_cmds) {
+            let continues_line = entries.len() > first;
+            match entries.last_mut() {
+                Some(entry) if continues_line && entry.has_runas(runas) => entry.push(cmd_spec),
+                _ => entries.push(Entry::new(runas, vec![cmd_spec], origin)),
+            }
+        }
+    }
+
+    entries
 }
 
 /// Process a raw parsed AST bit of RunAs + Command specifications:
@@ -169,13 +544,13 @@ fn distribute_tags(
 ) -> impl Iterator<Item = (Option<&RunAs>, (Tag, &Spec<Command>))> {
     runas_cmds.iter().scan(
         (None, Default::default()),
-        |(mut last_runas, tag), (runas, CommandSpec(mods, cmd))| {
-            last_runas = runas.as_ref().or(last_runas);
+        |(last_runas, tag), (runas, CommandSpec(mods, cmd))| {
+            *last_runas = runas.as_ref().or(*last_runas);
             for f in mods {
                 f(tag);
             }
 
-            Some((last_runas, (tag.clone(), cmd)))
+            Some((*last_runas, (tag.clone(), cmd)))
         },
     )
 }
@@ -243,12 +618,40 @@ impl<'a> WithInfo for (Tag, &'a Spec<Command>) {
 }
 
 /// Now follow a collection of functions used as closures for `find_item`
-fn match_user(user: &impl UnixUser) -> impl Fn(&UserSpecifier) -> bool + '_ {
+fn match_user<'a>(
+    user: &'a impl UnixUser,
+    sudoers: &'a Sudoers,
+) -> impl Fn(&UserSpecifier) -> bool + 'a {
+    let in_group_plugin = move |group: &str| {
+        sudoers
+            .group_plugin
+            .as_ref()
+            .zip(user.try_as_name())
+            .is_some_and(|(plugin, name)| plugin.is_member(name, group))
+    };
+    // a group that is not known to the system can be looked up using the group plugin
+    let always_query = sudoers.settings.flags.contains("always_query_group_plugin");
+
     move |spec| match spec {
         UserSpecifier::User(id) => match_identifier(user, id),
-        UserSpecifier::Group(Identifier::Name(name)) => user.in_group_by_name(name),
-        UserSpecifier::Group(Identifier::ID(num)) => user.in_group_by_gid(*num),
-        _ => todo!(), // nonunix-groups, netgroups, etc.
+        UserSpecifier::Group(Identifier::Name(name)) => {
+            user.in_group_by_name(name)
+                || (always_query
+                    && matches!(crate::system::Group::from_name(name), Ok(None))
+                    && in_group_plugin(name))
+        }
+        UserSpecifier::Group(Identifier::ID(num)) => {
+            user.in_group_by_gid(*num)
+                || (always_query
+                    && matches!(crate::system::Group::from_gid(*num), Ok(None))
+                    && in_group_plugin(&format!("#{num}")))
+        }
+        // like the original sudo, the host is not considered when matching a user netgroup
+        UserSpecifier::Netgroup(netgroup) => user
+            .try_as_name()
+            .is_some_and(|name| sudoers.netgroups.contains(netgroup, None, Some(name))),
+        UserSpecifier::NonunixGroup(Identifier::Name(name)) => in_group_plugin(name),
+        UserSpecifier::NonunixGroup(Identifier::ID(num)) => in_group_plugin(&format!("#{num}")),
     }
 }
 
@@ -275,18 +678,65 @@ fn match_group_alias(group: &impl UnixGroup) -> impl Fn(&UserSpecifier) -> bool
     }
 }
 
-fn match_token<T: basic_parser::Token + std::ops::Deref<Target = String>>(
-    text: &str,
-) -> (impl Fn(&T) -> bool + '_) {
-    move |token| token.as_str() == text
+fn match_host<'a>(
+    hostname: &'a str,
+    netgroups: &'a dyn NetgroupProvider,
+) -> impl Fn(&HostSpecifier) -> bool + 'a {
+    move |spec| match spec {
+        HostSpecifier::Host(name) => name.as_str() == hostname,
+        // like the original sudo, the user is not considered when matching a host netgroup
+        HostSpecifier::Netgroup(netgroup) => netgroups.contains(netgroup, Some(hostname), None),
+    }
 }
 
-fn match_command<'a>((cmd, args): (&'a Path, &'a [String])) -> (impl Fn(&Command) -> bool + 'a) {
-    move |(cmdpat, argpat)| {
-        cmdpat.matches_path(cmd) && argpat.as_ref().map_or(true, |vec| args == vec.as_ref())
+fn match_command<'a>(
+    cmd: &'a Executable<'a>,
+    args: &'a [String],
+) -> impl Fn(&Command) -> bool + 'a {
+    move |((cmdpat, argpat), digests)| {
+        cmdpat.matches_path(cmd.path())
+            && argpat.as_ref().map_or(true, |vec| {
+                if cmdpat.as_str() == "sudoedit" {
+                    match_edit_files(args, vec)
+                } else {
+                    args == vec.as_ref()
+                }
+            })
+            // the digest is checked last, since it requires reading the executable
+            && (digests.is_empty() || digests.iter().any(|digest| cmd.has_digest(digest)))
     }
 }
 
+/// The arguments of 'sudoedit' are file names that may contain wildcards; like in the original
+/// sudo, a wildcard in such a path will never match a '/'.
+fn match_edit_files(files: &[String], patterns: &[String]) -> bool {
+    let options = glob::MatchOptions {
+        require_literal_separator: true,
+        ..Default::default()
+    };
+
+    files.len() == patterns.len()
+        && files.iter().zip(patterns).all(|(file, pattern)| {
+            glob::Pattern::new(pattern).is_ok_and(|pattern| pattern.matches_with(file, options))
+        })
+}
+
+/// The names of the aliases that are referenced in a list of specifications
+fn alias_references<T>(list: &[Spec<T>]) -> impl Iterator<Item = &String> {
+    list.iter().filter_map(|spec| match spec {
+        Qualified::Allow(Meta::Alias(name)) | Qualified::Forbid(Meta::Alias(name)) => Some(name),
+        _ => None,
+    })
+}
+
+/// The aliases defined in an alias table, together with the aliases that each of them references
+fn alias_definitions<T>(table: &[Def<T>]) -> Vec<(&String, Vec<&String>)> {
+    table
+        .iter()
+        .map(|Def(name, members)| (name, alias_references(members).collect()))
+        .collect()
+}
+
 /// Find all the aliases that a object is a member of; this requires [sanitize_alias_table] to have run first;
 /// I.e. this function should not be "pub".
 
@@ -363,9 +813,49 @@ impl Default for Settings {
     }
 }
 
+impl Settings {
+    /// Change a setting as specified by a `Defaults` directive
+    fn set(&mut self, name: String, value: ConfigValue) {
+        use ConfigValue::*;
+
+        match value {
+            Flag(value) => {
+                if value {
+                    self.flags.insert(name);
+                } else {
+                    self.flags.remove(&name);
+                }
+            }
+            List(mode, values) => {
+                let slot: &mut _ = self.list.entry(name).or_default();
+                match mode {
+                    Mode::Set => *slot = values.into_iter().collect(),
+                    Mode::Add => slot.extend(values),
+                    Mode::Del => {
+                        for key in values {
+                            slot.remove(&key);
+                        }
+                    }
+                }
+            }
+            Text(value) => {
+                self.str_value.insert(name, value);
+            }
+            Enum(value) => {
+                self.enum_value.insert(name, value);
+            }
+            Num(value) => {
+                self.int_value.insert(name, value);
+            }
+        }
+    }
+}
+
 /// Process a sudoers-parsing file into a workable AST
-fn analyze(sudoers: impl IntoIterator<Item = basic_parser::Parsed<Sudo>>) -> (Sudoers, Vec<Error>) {
-    use ConfigValue::*;
+fn analyze(
+    path: &Path,
+    sudoers: impl IntoIterator<Item = basic_parser::Parsed<Sudo>>,
+) -> (Sudoers, Vec<Error>) {
     use Directive::*;
 
     let mut result: Sudoers = Default::default();
@@ -373,44 +863,57 @@ fn analyze(sudoers: impl IntoIterator<Item = basic_parser::Parsed<Sudo>>) -> (Su
     impl Sudoers {
         fn include(&mut self, path: &Path, diagnostics: &mut Vec<Error>, count: &mut u8) {
             if *count >= INCLUDE_LIMIT {
-                diagnostics.push(Error(
-                    None,
-                    format!("include file limit reached opening '{}'", path.display()),
-                ))
-            } else if let Ok(subsudoer) = read_sudoers(path) {
-                *count += 1;
-                self.process(subsudoer, diagnostics, count)
+                diagnostics.push(Error {
+                    source: Some(path.to_path_buf()),
+                    location: None,
+                    message: format!("include file limit reached opening '{}'", path.display()),
+                })
             } else {
-                diagnostics.push(Error(
-                    None,
-                    format!("cannot open sudoers file '{}'", path.display()),
-                ))
+                match read_sudoers(path) {
+                    Ok(subsudoer) => {
+                        *count += 1;
+                        self.process(path, subsudoer, diagnostics, count)
+                    }
+                    Err(err) => diagnostics.push(Error {
+                        source: Some(path.to_path_buf()),
+                        location: None,
+                        message: format!("cannot open sudoers file '{}': {err}", path.display()),
+                    }),
+                }
             }
         }
 
         fn process(
             &mut self,
+            path: &Path,
             sudoers: impl IntoIterator<Item = basic_parser::Parsed<Sudo>>,
             diagnostics: &mut Vec<Error>,
             safety_count: &mut u8,
         ) {
+            let file: Rc<Path> = path.into();
             for item in sudoers {
                 match item {
                     Ok(line) => match line {
                         Sudo::LineComment => {}
 
-                        Sudo::Spec(permission) => self.rules.push(permission),
+                        Sudo::Spec(mut permission) => {
+                            permission.origin.file = Some(file.clone());
+                            self.rules.push(permission)
+                        }
 
                         Sudo::Decl(UserAlias(mut def)) => self.aliases.user.1.append(&mut def),
                         Sudo::Decl(HostAlias(mut def)) => self.aliases.host.1.append(&mut def),
                         Sudo::Decl(CmndAlias(mut def)) => self.aliases.cmnd.1.append(&mut def),
                         Sudo::Decl(RunasAlias(mut def)) => self.aliases.runas.1.append(&mut def),
 
-                        Sudo::Decl(Defaults(params)) => {
+                        Sudo::Decl(Defaults(params, ConfigScope::Generic)) => {
                             for (name, value) in params {
-                                self.set_default(name, value)
+                                self.settings.set(name, value)
                             }
                         }
+                        Sudo::Decl(Defaults(params, scope)) => {
+                            self.scoped_settings.push((scope, params))
+                        }
 
                         Sudo::Include(path) => {
                             self.include(path.as_ref(), diagnostics, safety_count)
@@ -418,7 +921,11 @@ fn analyze(sudoers: impl IntoIterator<Item = basic_parser::Parsed<Sudo>>) -> (Su
 
                         Sudo::IncludeDir(path) => {
                             let Ok(files) = std::fs::read_dir(&path) else {
-                                diagnostics.push(Error(None, format!("cannot open sudoers file {path}")));
+                                diagnostics.push(Error {
+                                    source: Some(file.to_path_buf()),
+                                    location: None,
+                                    message: format!("cannot open sudoers directory '{path}'"),
+                                });
                                 continue;
                             };
                             let mut safe_files = files
@@ -439,50 +946,41 @@ fn analyze(sudoers: impl IntoIterator<Item = basic_parser::Parsed<Sudo>>) -> (Su
                         }
                     },
 
-                    Err(basic_parser::Status::Fatal(pos, error)) => {
-                        diagnostics.push(Error(Some(pos), error))
-                    }
+                    Err(basic_parser::Status::Fatal(pos, message)) => diagnostics.push(Error {
+                        source: Some(file.to_path_buf()),
+                        location: Some(pos),
+                        message,
+                    }),
                     Err(_) => panic!("internal parser error"),
                 }
             }
         }
-
-        fn set_default(&mut self, name: String, value: ConfigValue) {
-            match value {
-                Flag(value) => {
-                    if value {
-                        self.settings.flags.insert(name);
-                    } else {
-                        self.settings.flags.remove(&name);
-                    }
-                }
-                List(mode, values) => {
-                    let slot: &mut _ = self.settings.list.entry(name).or_default();
-                    match mode {
-                        Mode::Set => *slot = values.into_iter().collect(),
-                        Mode::Add => slot.extend(values),
-                        Mode::Del => {
-                            for key in values {
-                                slot.remove(&key);
-                            }
-                        }
-                    }
-                }
-                Text(value) => {
-                    self.settings.str_value.insert(name, value);
-                }
-                Enum(value) => {
-                    self.settings.enum_value.insert(name, value);
-                }
-                Num(value) => {
-                    self.settings.int_value.insert(name, value);
-                }
-            }
-        }
     }
 
     let mut diagnostics = vec![];
-    result.process(sudoers, &mut diagnostics, &mut 0);
+    result.process(path, sudoers, &mut diagnostics, &mut 0);
+
+    // like the original sudo, the Defaults for hosts are applied first, then those for users,
+    // runas users and finally commands; otherwise, the order of the file is kept
+    result
+        .scoped_settings
+        .sort_by_key(|(scope, _)| match scope {
+            ConfigScope::Generic | ConfigScope::Host(_) => 0,
+            ConfigScope::User(_) => 1,
+            ConfigScope::RunAs(_) => 2,
+            ConfigScope::Command(_) => 3,
+        });
+
+    if let Some(Some(plugin)) = result.settings.str_value.get("group_plugin") {
+        match load_group_plugin(plugin) {
+            Ok(provider) => result.group_plugin = Some(provider),
+            Err(message) => diagnostics.push(Error {
+                source: Some(path.to_path_buf()),
+                location: None,
+                message,
+            }),
+        }
+    }
 
     let alias = &mut result.aliases;
     alias.user.0 = sanitize_alias_table(&alias.user.1, &mut diagnostics);
@@ -493,6 +991,25 @@ fn analyze(sudoers: impl IntoIterator<Item = basic_parser::Parsed<Sudo>>) -> (Su
     (result, diagnostics)
 }
 
+/// Load the group plugin configured as `group_plugin="plugin.so arguments"`; instead of loading
+/// shared objects, the plugins that sudo-rs knows about are built in.
+fn load_group_plugin(setting: &str) -> Result<Box<dyn GroupProvider>, String> {
+    let mut words = setting.split_whitespace();
+    let plugin = words.next().unwrap_or_default();
+
+    match Path::new(plugin).file_name().and_then(|name| name.to_str()) {
+        Some(GroupFile::PLUGIN_NAME) => {
+            let Some(path) = words.next() else {
+                return Err(format!("group plugin {plugin}: no group file specified"));
+            };
+            GroupFile::open(path)
+                .map(|file| Box::new(file) as Box<dyn GroupProvider>)
+                .map_err(|err| format!("group plugin {plugin}: cannot open '{path}': {err}"))
+        }
+        _ => Err(format!("unsupported group plugin: '{plugin}'")),
+    }
+}
+
 /// Alias definition inin a Sudoers file can come in any order; and aliases can refer to other aliases, etc.
 /// It is much easier if they are presented in a "definitional order" (i.e. aliases that use other aliases occur later)
 /// At the same time, this is a good place to detect problems in the aliases, such as unknown aliases and cycles.
@@ -515,7 +1032,11 @@ fn sanitize_alias_table<T>(table: &Vec<Def<T>>, diagnostics: &mut Vec<Error>) ->
 
     impl<T> Visitor<'_, T> {
         fn complain(&mut self, text: String) {
-            self.diagnostics.push(Error(None, text))
+            self.diagnostics.push(Error {
+                source: None,
+                location: None,
+                message: text,
+            })
         }
 
         fn visit(&mut self, pos: usize) {
@@ -1,13 +1,15 @@
-use super::Sudoers;
+use super::{settings_for, Settings, Sudoers};
 
 use super::Judgement;
+use crate::system::{interface::UnixUser, time::Duration};
 /// Data types and traits that represent what the "terms and conditions" are after a succesful
 /// permission check.
 ///
 /// The trait definitions can be part of some global crate in the future, if we support more
 /// than just the sudoers file.
 use std::collections::HashSet;
-use std::path::Path;
+use std::fs::File;
+use std::path::{Path, PathBuf};
 
 pub trait Policy {
     fn authorization(&self) -> Authorization {
@@ -18,10 +20,61 @@ pub trait Policy {
         DirChange::Strict(None)
     }
 
+    fn authentication(&self) -> Authentication {
+        Authentication::from(&Settings::default())
+    }
+
     fn env_keep(&self) -> &HashSet<String>;
     fn env_check(&self) -> &HashSet<String>;
+    fn env_delete(&self) -> &HashSet<String>;
+
+    fn env_reset(&self) -> bool;
+
+    /// Whether the user may set environment variables for the command that the other
+    /// environment settings would not allow.
+    fn setenv(&self) -> bool {
+        false
+    }
+
+    /// Whether HOME is always set to the home directory of the target user.
+    fn always_set_home(&self) -> bool {
+        false
+    }
+
+    /// Whether HOME is set to the home directory of the target user when running a shell.
+    fn set_home(&self) -> bool {
+        false
+    }
+
+    /// Whether LOGNAME and USER are set to the target user if the environment is not reset.
+    fn set_logname(&self) -> bool {
+        true
+    }
 
     fn secure_path(&self) -> Option<String>;
+
+    /// A file with variables that are added to the environment of the command, which are
+    /// trusted as part of the policy.
+    fn env_file(&self) -> Option<String> {
+        None
+    }
+
+    /// A file with variables that are added to the environment of the command, which are
+    /// subject to the same checks as the environment of the invoking user.
+    fn restricted_env_file(&self) -> Option<String> {
+        None
+    }
+
+    fn editor(&self) -> String;
+
+    /// Whether the editor may be chosen by the user through `SUDO_EDITOR`, `VISUAL` or `EDITOR`
+    fn env_editor(&self) -> bool;
+
+    /// The executable that the policy has checked the digest of; this open file should be
+    /// executed, instead of the file that the path of the command refers to.
+    fn command_file(&self) -> Option<&File> {
+        None
+    }
 }
 
 #[must_use]
@@ -31,6 +84,96 @@ pub enum Authorization {
     Forbidden,
 }
 
+/// The settings that determine how a user is authenticated, and how long a successful
+/// authentication remains valid.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Authentication {
+    pub credential: AuthenticatingUser,
+    /// A zero timeout means that the user always has to authenticate, and a negative timeout
+    /// means that an authentication never expires.
+    pub timestamp_timeout: Duration,
+    pub timestamp_type: TimestampType,
+    /// The number of times the user can try to enter their password
+    pub passwd_tries: usize,
+    /// How long the user has to enter their password, if there is a limit
+    pub passwd_timeout: Option<std::time::Duration>,
+    pub lecture: Lecture,
+    /// A custom text to lecture the user with, instead of the standard one
+    pub lecture_file: Option<PathBuf>,
+    /// The password prompt to use, before escape sequences are expanded
+    pub passprompt: Option<String>,
+    /// Whether `passprompt` should also replace prompts other than the standard one of PAM
+    pub passprompt_override: bool,
+}
+
+/// To which invocations of sudo a successful authentication applies
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimestampType {
+    /// All invocations by the same user
+    Global,
+    /// Invocations from the same terminal session, or from the same parent process if there
+    /// is no terminal
+    Tty,
+    /// Invocations from the same parent process
+    Ppid,
+}
+
+/// Whose password has to be entered to authenticate
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthenticatingUser {
+    InvokingUser,
+    Root,
+    TargetUser,
+}
+
+/// When to lecture a user before asking for their password
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lecture {
+    Never,
+    /// Only the first time the user has to authenticate
+    Once,
+    Always,
+}
+
+impl From<&Settings> for Authentication {
+    fn from(settings: &Settings) -> Self {
+        let flag = |name| settings.flags.contains(name);
+        Authentication {
+            // `runaspw` asks for the password of the default runas user, which is root
+            credential: if flag("rootpw") || flag("runaspw") {
+                AuthenticatingUser::Root
+            } else if flag("targetpw") {
+                AuthenticatingUser::TargetUser
+            } else {
+                AuthenticatingUser::InvokingUser
+            },
+            timestamp_timeout: Duration::seconds(settings.int_value["timestamp_timeout"] as i64),
+            timestamp_type: match &*settings.enum_value["timestamp_type"] {
+                "global" => TimestampType::Global,
+                "ppid" => TimestampType::Ppid,
+                _ => TimestampType::Tty,
+            },
+            passwd_tries: settings.int_value["passwd_tries"] as usize,
+            passwd_timeout: match settings.int_value["passwd_timeout"] {
+                secs if secs <= 0 => None,
+                secs => Some(std::time::Duration::from_secs(secs as u64)),
+            },
+            lecture: match &*settings.enum_value["lecture"] {
+                "never" => Lecture::Never,
+                "always" => Lecture::Always,
+                _ => Lecture::Once,
+            },
+            lecture_file: settings.str_value["lecture_file"]
+                .as_deref()
+                .map(PathBuf::from),
+            passprompt: settings.str_value["passprompt"]
+                .as_deref()
+                .map(str::to_string),
+            passprompt_override: flag("passprompt_override"),
+        }
+    }
+}
+
 #[must_use]
 #[cfg_attr(test, derive(Debug, PartialEq))]
 pub enum DirChange<'a> {
@@ -57,6 +200,36 @@ impl Policy for Judgement {
         &self.settings.list["env_check"]
     }
 
+    fn env_delete(&self) -> &HashSet<String> {
+        &self.settings.list["env_delete"]
+    }
+
+    fn env_reset(&self) -> bool {
+        self.settings.flags.contains("env_reset")
+    }
+
+    fn setenv(&self) -> bool {
+        // the SETENV and NOSETENV tags take precedence over the setenv setting
+        let tag = self.flags.as_ref().and_then(|tag| tag.setenv);
+        tag.unwrap_or_else(|| self.settings.flags.contains("setenv"))
+    }
+
+    fn always_set_home(&self) -> bool {
+        self.settings.flags.contains("always_set_home")
+    }
+
+    fn set_home(&self) -> bool {
+        self.settings.flags.contains("set_home")
+    }
+
+    fn set_logname(&self) -> bool {
+        self.settings.flags.contains("set_logname")
+    }
+
+    fn authentication(&self) -> Authentication {
+        Authentication::from(&self.settings)
+    }
+
     fn chdir(&self) -> DirChange {
         match self.flags.as_ref().expect("not authorized").cwd.as_ref() {
             None => DirChange::Strict(None),
@@ -70,18 +243,64 @@ impl Policy for Judgement {
             .as_ref()
             .map(|s| s.to_string())
     }
+
+    fn env_file(&self) -> Option<String> {
+        self.settings.str_value["env_file"]
+            .as_ref()
+            .map(|s| s.to_string())
+    }
+
+    fn restricted_env_file(&self) -> Option<String> {
+        self.settings.str_value["restricted_env_file"]
+            .as_ref()
+            .map(|s| s.to_string())
+    }
+
+    fn editor(&self) -> String {
+        self.settings.str_value["editor"]
+            .as_deref()
+            .unwrap_or_default()
+            .to_string()
+    }
+
+    fn env_editor(&self) -> bool {
+        self.settings.flags.contains("env_editor")
+    }
+
+    fn command_file(&self) -> Option<&File> {
+        self.command_file.as_ref()
+    }
 }
 
+/// The settings that are needed before a request can be judged; they are determined by the
+/// `Defaults` that apply to the invoking user on the given host.
 pub trait PreJudgementPolicy {
-    fn secure_path(&self) -> Option<String>;
+    fn secure_path(&self, am_user: &impl UnixUser, on_host: &str) -> Option<String>;
+
+    fn editor(&self, am_user: &impl UnixUser, on_host: &str) -> String;
+
+    fn env_editor(&self, am_user: &impl UnixUser, on_host: &str) -> bool;
 }
 
 impl PreJudgementPolicy for Sudoers {
-    fn secure_path(&self) -> Option<String> {
-        self.settings.str_value["secure_path"]
+    fn secure_path(&self, am_user: &impl UnixUser, on_host: &str) -> Option<String> {
+        settings_for(self, am_user, on_host, None).str_value["secure_path"]
             .as_ref()
             .map(|s| s.to_string())
     }
+
+    fn editor(&self, am_user: &impl UnixUser, on_host: &str) -> String {
+        settings_for(self, am_user, on_host, None).str_value["editor"]
+            .as_deref()
+            .unwrap_or_default()
+            .to_string()
+    }
+
+    fn env_editor(&self, am_user: &impl UnixUser, on_host: &str) -> bool {
+        settings_for(self, am_user, on_host, None)
+            .flags
+            .contains("env_editor")
+    }
 }
 
 #[cfg(test)]
@@ -33,6 +33,10 @@ impl UnixUser for Named {
     fn is_root(&self) -> bool {
         self.0 == "root"
     }
+
+    fn try_as_name(&self) -> Option<&str> {
+        Some(self.0)
+    }
 }
 
 impl UnixGroup for Named {
@@ -55,7 +59,7 @@ macro_rules! request {
 
 macro_rules! sudoer {
     ($($e:expr),*) => {
-        parse_lines(&mut [$($e),*, ""].join("\n").chars().peekable())
+        parse_lines(&mut char_stream::PeekableWithPos::new([$($e),*, ""].join("\n").chars()))
             .into_iter()
             .map(|x| Ok::<_,basic_parser::Status>(x.unwrap()))
     }
@@ -77,19 +81,19 @@ fn permission_test() {
 
     macro_rules! FAIL {
         ([$($sudo:expr),*], $user:expr => $req:expr, $server:expr; $command:expr) => {
-            let (Sudoers { rules,aliases,settings }, _) = analyze(sudoer![$($sudo),*]);
+            let (sudoers, _) = analyze(Path::new("/etc/fakesudoers"), sudoer![$($sudo),*]);
             let cmdvec = $command.split_whitespace().map(String::from).collect::<Vec<_>>();
             let req = Request { user: $req.0, group: $req.1, command: cmdvec[0].as_ref(), arguments: &cmdvec[1..].to_vec() };
-            assert_eq!(Sudoers { rules, aliases, settings }.check(&Named($user), $server, req).flags, None);
+            assert_eq!(sudoers.check(&Named($user), $server, req).flags, None);
         }
     }
 
     macro_rules! pass {
         ([$($sudo:expr),*], $user:expr => $req:expr, $server:expr; $command:expr $(=> [$($key:ident : $val:expr),*])?) => {
-            let (Sudoers { rules,aliases,settings }, _) = analyze(sudoer![$($sudo),*]);
+            let (sudoers, _) = analyze(Path::new("/etc/fakesudoers"), sudoer![$($sudo),*]);
             let cmdvec = $command.split_whitespace().map(String::from).collect::<Vec<_>>();
             let req = Request { user: $req.0, group: $req.1, command: &cmdvec[0].as_ref(), arguments: &cmdvec[1..].to_vec() };
-            let result = Sudoers { rules, aliases, settings }.check(&Named($user), $server, req).flags;
+            let result = sudoers.check(&Named($user), $server, req).flags;
             assert!(!result.is_none());
             $(
                 let result = result.unwrap();
@@ -118,6 +122,11 @@ fn permission_test() {
     pass!(["user ALL=(ALL:ALL) CWD=/ /bin/foo, CWD=* /bin/bar"], "user" => root(), "server"; "/bin/bar" => [cwd: Some(ChDir::Any)]);
     pass!(["user ALL=(ALL:ALL) CWD=/bin CWD=* /bin/foo"], "user" => root(), "server"; "/bin/foo" => [cwd: Some(ChDir::Any)]);
     pass!(["user ALL=(ALL:ALL) CWD=/usr/bin NOPASSWD: /bin/foo"], "user" => root(), "server"; "/bin/foo" => [passwd: false, cwd: Some(ChDir::Path("/usr/bin".into()))]);
+    pass!(["user ALL=(ALL:ALL) /bin/foo"], "user" => root(), "server"; "/bin/foo" => [setenv: None]);
+    pass!(["user ALL=(ALL:ALL) SETENV: /bin/foo"], "user" => root(), "server"; "/bin/foo" => [setenv: Some(true)]);
+    pass!(["user ALL=(ALL:ALL) SETENV: /bin/foo, NOSETENV: /bin/bar"], "user" => root(), "server"; "/bin/bar" => [setenv: Some(false)]);
+    pass!(["user ALL=(ALL:ALL) ALL"], "user" => root(), "server"; "/bin/foo" => [setenv: Some(true)]);
+    pass!(["user ALL=(ALL:ALL) NOSETENV: ALL"], "user" => root(), "server"; "/bin/foo" => [setenv: Some(false)]);
     //note: original sudo does not allow the below
     pass!(["user ALL=(ALL:ALL) NOPASSWD: CWD=/usr/bin /bin/foo"], "user" => root(), "server"; "/bin/foo" => [passwd: false, cwd: Some(ChDir::Path("/usr/bin".into()))]);
 
@@ -222,17 +231,458 @@ fn permission_test() {
     pass!(["Runas_Alias TIME=%wheel,!!sudo","user ALL=(TIME) ALL"], "user" => request! { wheel, wheel }, "vm"; "/bin/ls");
 
     pass!(["Runas_Alias \\"," TIME=%wheel\\",",sudo # hallo","user ALL\\","=(TIME) ALL"], "user" => request! { wheel, wheel }, "vm"; "/bin/ls");
+
+    pass!(["user ALL=sudoedit /etc/motd"], "user" => root(), "server"; "sudoedit /etc/motd");
+    FAIL!(["user ALL=sudoedit /etc/motd"], "user" => root(), "server"; "sudoedit /etc/hosts");
+    FAIL!(["user ALL=sudoedit /etc/motd"], "user" => root(), "server"; "/etc/motd");
+    pass!(["user ALL=sudoedit"], "user" => root(), "server"; "sudoedit /etc/motd");
+    pass!(["user ALL=ALL"], "user" => root(), "server"; "sudoedit /etc/motd");
+    pass!(["user ALL=sudoedit /etc/*"], "user" => root(), "server"; "sudoedit /etc/motd");
+    FAIL!(["user ALL=sudoedit /etc/*"], "user" => root(), "server"; "sudoedit /etc/ssh/sshd_config");
+    FAIL!(["user ALL=sudoedit /etc/*"], "user" => root(), "server"; "sudoedit /etc/motd /etc/hosts");
+    pass!(["user ALL=sudoedit /etc/*/*"], "user" => root(), "server"; "sudoedit /etc/ssh/sshd_config");
+    pass!(["user ALL=sudoedit /etc/motd /etc/hosts"], "user" => root(), "server"; "sudoedit /etc/motd /etc/hosts");
+    FAIL!(["user ALL=ALL, !sudoedit /etc/*"], "user" => root(), "server"; "sudoedit /etc/motd");
+    pass!(["user ALL=NOPASSWD: sudoedit /etc/motd, /bin/ls"], "user" => root(), "server"; "/bin/ls" => [passwd: false]);
+    SYNTAX!(["user ALL=sudoedit etc/motd"]);
+}
+
+#[test]
+fn list_test() {
+    macro_rules! list {
+        ([$($sudo:expr),*], $user:expr, $server:expr) => {{
+            let (sudoers, _) = analyze(Path::new("/etc/fakesudoers"), sudoer![$($sudo),*]);
+            let entries = sudoers.matching_entries(&Named($user), $server);
+            entries.iter().map(|entry| entry.to_string()).collect::<Vec<_>>()
+        }}
+    }
+
+    assert!(list!(["user ALL=(ALL:ALL) ALL"], "nobody", "server").is_empty());
+    assert!(list!(["user laptop=(ALL:ALL) ALL"], "user", "server").is_empty());
+    assert_eq!(
+        list!(["user ALL=(ALL:ALL) ALL"], "user", "server"),
+        ["(ALL : ALL) ALL"]
+    );
+    assert_eq!(
+        list!(["user ALL=/bin/foo"], "user", "server"),
+        ["(root) /bin/foo"]
+    );
+    assert_eq!(
+        list!(
+            ["user ALL=(ALL) /bin/foo, NOPASSWD: /bin/bar, /bin/baz arg"],
+            "user",
+            "server"
+        ),
+        ["(ALL) /bin/foo, NOPASSWD: /bin/bar, /bin/baz arg"]
+    );
+    assert_eq!(
+        list!(
+            ["user ALL=(root) CWD=/usr /bin/foo \"\", (%wheel,!#1001) !/bin/bar"],
+            "user",
+            "server"
+        ),
+        [
+            "(root) CWD=/usr /bin/foo \"\"",
+            "(%wheel, !#1001) CWD=/usr !/bin/bar"
+        ]
+    );
+    assert_eq!(
+        list!(
+            ["user ALL=SETENV: /bin/foo, NOSETENV: /bin/bar, ALL"],
+            "user",
+            "server"
+        ),
+        ["(root) SETENV: /bin/foo, NOSETENV: /bin/bar, ALL"]
+    );
+    assert_eq!(
+        list!(
+            [
+                "User_Alias STAFF=user",
+                "Host_Alias MACHINE=server",
+                "STAFF MACHINE=(:sudo) ALL",
+                "ALL ALL=(user) /bin/ls"
+            ],
+            "user",
+            "server"
+        ),
+        ["( : sudo) ALL", "(user) /bin/ls"]
+    );
+}
+
+#[test]
+fn list_permission_test() {
+    macro_rules! check {
+        ([$($sudo:expr),*], $user:expr => $list_user:expr, $server:expr) => {{
+            let (sudoers, _) = analyze(Path::new("/etc/fakesudoers"), sudoer![$($sudo),*]);
+            sudoers.check_list_permission(&Named($user), $server, &Named($list_user))
+        }}
+    }
+
+    let allowed = |must_authenticate| Authorization::Allowed { must_authenticate };
+
+    assert_eq!(
+        check!(["user ALL=ALL"], "user" => "user", "server"),
+        allowed(true)
+    );
+    assert_eq!(
+        check!(["user ALL=NOPASSWD: /bin/ls, /bin/cat"], "user" => "user", "server"),
+        allowed(false)
+    );
+    assert_eq!(
+        check!(["Defaults listpw=all", "user ALL=NOPASSWD: /bin/ls, PASSWD: /bin/cat"], "user" => "user", "server"),
+        allowed(true)
+    );
+    assert_eq!(
+        check!(["Defaults !listpw", "user ALL=ALL"], "user" => "user", "server"),
+        allowed(false)
+    );
+    assert_eq!(
+        check!(["user ALL=ALL"], "other" => "other", "server"),
+        Authorization::Forbidden
+    );
+
+    assert_eq!(
+        check!(["user ALL=/bin/ls"], "user" => "other", "server"),
+        Authorization::Forbidden
+    );
+    assert_eq!(
+        check!(["user ALL=list"], "user" => "other", "server"),
+        allowed(true)
+    );
+    assert_eq!(
+        check!(["user ALL=NOPASSWD: list"], "user" => "other", "server"),
+        allowed(false)
+    );
+    assert_eq!(
+        check!(["user ALL=(ALL) ALL"], "user" => "other", "server"),
+        allowed(true)
+    );
+    assert_eq!(
+        check!(["user laptop=list"], "user" => "other", "server"),
+        Authorization::Forbidden
+    );
+    assert_eq!(check!([""], "root" => "other", "server"), allowed(false));
+}
+
+#[test]
+fn validate_permission_test() {
+    macro_rules! check {
+        ([$($sudo:expr),*], $user:expr, $server:expr) => {{
+            let (sudoers, _) = analyze(Path::new("/etc/fakesudoers"), sudoer![$($sudo),*]);
+            sudoers.check_validate_permission(&Named($user), $server)
+        }}
+    }
+
+    let allowed = |must_authenticate| Authorization::Allowed { must_authenticate };
+
+    assert_eq!(check!(["user ALL=ALL"], "user", "server"), allowed(true));
+    assert_eq!(
+        check!(["user ALL=NOPASSWD: ALL"], "user", "server"),
+        allowed(false)
+    );
+    assert_eq!(
+        check!(
+            ["user ALL=NOPASSWD: /bin/ls, PASSWD: /bin/cat"],
+            "user",
+            "server"
+        ),
+        allowed(true)
+    );
+    assert_eq!(
+        check!(
+            [
+                "Defaults verifypw=any",
+                "user ALL=NOPASSWD: /bin/ls, PASSWD: /bin/cat"
+            ],
+            "user",
+            "server"
+        ),
+        allowed(false)
+    );
+    assert_eq!(
+        check!(
+            ["Defaults verifypw=always", "user ALL=NOPASSWD: ALL"],
+            "user",
+            "server"
+        ),
+        allowed(true)
+    );
+    assert_eq!(
+        check!(["Defaults !verifypw", "user ALL=ALL"], "user", "server"),
+        allowed(false)
+    );
+    assert_eq!(
+        check!(["user laptop=ALL"], "user", "server"),
+        Authorization::Forbidden
+    );
+    assert_eq!(check!([""], "root", "server"), allowed(false));
+}
+
+#[test]
+fn long_list_test() {
+    let (sudoers, _) = analyze(
+        Path::new("/etc/fakesudoers"),
+        sudoer![
+            "Defaults env_reset",
+            "user ALL=(ALL:ALL) ALL",
+            "user ALL=/bin/foo, NOPASSWD: /bin/bar, /bin/baz, (:wheel) CWD=/usr /bin/qux"
+        ],
+    );
+    let entries = sudoers.matching_entries(&Named("user"), "server");
+    let entries = entries
+        .iter()
+        .map(|entry| format!("{entry:#}"))
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        entries,
+        [
+            "Sudoers entry: /etc/fakesudoers:2\n    RunAsUsers: ALL\n    RunAsGroups: ALL\n    Commands:\n\tALL",
+            "Sudoers entry: /etc/fakesudoers:3\n    RunAsUsers: root\n    Commands:\n\t/bin/foo\n\n\
+             Sudoers entry: /etc/fakesudoers:3\n    RunAsUsers: root\n    Options: !authenticate\n    Commands:\n\t/bin/bar\n\t/bin/baz",
+            "Sudoers entry: /etc/fakesudoers:3\n    RunAsGroups: wheel\n    Options: !authenticate\n    Cwd: /usr\n    Commands:\n\t/bin/qux",
+        ]
+    );
+}
+
+#[test]
+fn digest_test() {
+    use std::io::Write;
+
+    const SHA224: &str = "34a9980a40400fff79a36668bc97bf562175476de8d5b4d34e8bd712";
+    const SHA256: &str = "bfdeaeb08cffb6a36438bcd12dda25417e3cdd36f1e7e482a2849d539225288b";
+    const SHA256_BASE64: &str = "v96usIz/tqNkOLzRLdolQX483Tbx5+SCooSdU5IlKIs=";
+    const WRONG: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    file.write_all(b"#!/bin/sh\necho hello\n").unwrap();
+    let path = file.path().to_str().unwrap();
+
+    let judge = |lines: &[&str]| {
+        let (sudoers, errors) = analyze(
+            Path::new("/etc/fakesudoers"),
+            parse_lines(&mut char_stream::PeekableWithPos::new(
+                [lines, &[""]].concat().join("\n").chars(),
+            )),
+        );
+        assert!(errors.is_empty());
+        let (user, group) = request! { root };
+        let req = Request {
+            user,
+            group,
+            command: path.as_ref(),
+            arguments: &[],
+        };
+        sudoers.check(&Named("user"), "server", req)
+    };
+
+    let judgement = judge(&[&format!("user ALL = sha256:{SHA256} {path}")]);
+    assert!(judgement.flags.is_some());
+    // the file that was checked is the one that should be executed
+    assert!(judgement.command_file.is_some());
+
+    assert!(
+        judge(&[&format!("user ALL = sha256:{SHA256_BASE64} {path}")])
+            .flags
+            .is_some()
+    );
+    assert!(judge(&[&format!("user ALL = sha256:{WRONG} {path}")])
+        .flags
+        .is_none());
+    assert!(judge(&[&format!(
+        "user ALL = sha256:{WRONG}, sha224:{SHA224} {path}"
+    )])
+    .flags
+    .is_some());
+    assert!(judge(&[&format!("user ALL = sha256:{SHA256} /bin/ls")])
+        .flags
+        .is_none());
+    assert!(
+        judge(&[&format!("user ALL = ALL, !sha256:{SHA256} {path}")])
+            .flags
+            .is_none()
+    );
+    assert!(judge(&[
+        &format!("Cmnd_Alias PINNED = sha256:{SHA256} {path}"),
+        "user ALL = PINNED"
+    ])
+    .flags
+    .is_some());
+
+    let judgement = judge(&[&format!("user ALL = {path}")]);
+    assert!(judgement.flags.is_some());
+    assert!(judgement.command_file.is_none());
+
+    assert!(parse_string::<Sudo>("user ALL = sha256:0123 /bin/ls").is_err());
+    assert!(parse_string::<Sudo>(&format!("user ALL = sha256:{SHA256}, /bin/ls")).is_err());
+    assert!(parse_string::<Sudo>(&format!("user ALL = sha1:{SHA256} /bin/ls")).is_err());
+
+    let line = format!("user ALL = sha256:{SHA256_BASE64},sha224:{SHA224} /bin/ls");
+    let (sudoers, _) = analyze(Path::new("/etc/fakesudoers"), sudoer![line.as_str()]);
+    let entries = sudoers.matching_entries(&Named("user"), "server");
+    assert_eq!(
+        entries[0].to_string(),
+        format!("(root) sha256:{SHA256},sha224:{SHA224} /bin/ls")
+    );
+}
+
+#[test]
+fn netgroup_test() {
+    use crate::system::netgroup::NetgroupFile;
+
+    macro_rules! check {
+        ([$($sudo:expr),*], $user:expr, $server:expr) => {{
+            let (mut sudoers, _) = analyze(Path::new("/etc/fakesudoers"), sudoer![$($sudo),*]);
+            sudoers.set_netgroup_provider(NetgroupFile::parse("admins (,user,)\nservers (server,-,)\n"));
+            let (user, group) = request! { root };
+            let req = Request { user, group, command: "/bin/ls".as_ref(), arguments: &[] };
+            sudoers.check(&Named($user), $server, req).flags.is_some()
+        }};
+    }
+
+    assert!(check!(["+admins ALL=ALL"], "user", "server"));
+    assert!(!check!(["+admins ALL=ALL"], "other", "server"));
+    assert!(!check!(["ALL, !+admins ALL=ALL"], "user", "server"));
+    assert!(check!(["ALL +servers=ALL"], "other", "server"));
+    assert!(!check!(["ALL +servers=ALL"], "other", "laptop"));
+    assert!(check!(["ALL laptop, +servers=ALL"], "other", "laptop"));
+    assert!(check!(
+        [
+            "User_Alias ADMINS = +admins",
+            "Host_Alias SERVERS = +servers",
+            "ADMINS SERVERS=ALL"
+        ],
+        "user",
+        "server"
+    ));
+    assert!(!check!(
+        [
+            "User_Alias ADMINS = +admins",
+            "Host_Alias SERVERS = +servers",
+            "ADMINS SERVERS=ALL"
+        ],
+        "user",
+        "laptop"
+    ));
+}
+
+#[test]
+fn group_plugin_test() {
+    use crate::system::group_plugin::GroupFile;
+
+    macro_rules! check {
+        ([$($sudo:expr),*], $user:expr) => {{
+            let (mut sudoers, _) = analyze(Path::new("/etc/fakesudoers"), sudoer![$($sudo),*]);
+            sudoers.set_group_provider(GroupFile::parse("ldap-admins:*:4000:user\n"));
+            let (user, group) = request! { root };
+            let req = Request { user, group, command: "/bin/ls".as_ref(), arguments: &[] };
+            sudoers.check(&Named($user), "server", req).flags.is_some()
+        }};
+    }
+
+    assert!(check!(["%:ldap-admins ALL=ALL"], "user"));
+    assert!(check!(["%:#4000 ALL=ALL"], "user"));
+    assert!(!check!(["%:ldap-admins ALL=ALL"], "other"));
+    assert!(!check!(["%:wheel ALL=ALL"], "user"));
+    assert!(check!(
+        ["User_Alias ADMINS = %:ldap-admins", "ADMINS ALL=ALL"],
+        "user"
+    ));
+
+    // only with always_query_group_plugin are ordinary groups looked up using the plugin
+    assert!(!check!(["%ldap-admins ALL=ALL"], "user"));
+    assert!(check!(
+        ["Defaults always_query_group_plugin", "%ldap-admins ALL=ALL"],
+        "user"
+    ));
+
+    // without a group plugin, no one is a member of a non-Unix group
+    let (sudoers, _) = analyze(
+        Path::new("/etc/fakesudoers"),
+        sudoer!["%:ldap-admins ALL=ALL"],
+    );
+    let (user, group) = request! { root };
+    let req = Request {
+        user,
+        group,
+        command: "/bin/ls".as_ref(),
+        arguments: &[],
+    };
+    assert!(sudoers.check(&Named("user"), "server", req).flags.is_none());
+
+    let (_, errors) = analyze(
+        Path::new("/etc/fakesudoers"),
+        sudoer!["Defaults group_plugin=\"sssd.so\""],
+    );
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].message, "unsupported group plugin: 'sssd.so'");
+}
+
+#[test]
+fn alias_warnings_test() {
+    let (sudoers, errors) = analyze(
+        Path::new("/etc/fakesudoers"),
+        sudoer![
+            "User_Alias ADMINS = user, OPERATORS",
+            "User_Alias OPERATORS = %wheel",
+            "User_Alias UNUSED = root",
+            "Host_Alias SERVERS = server",
+            "Cmnd_Alias SHELLS = /bin/sh",
+            "Host_Alias DESKTOPS = desktop",
+            "Defaults@DESKTOPS !lecture",
+            "ADMINS SERVERS = (TARGETS) SHELLS, EDITORS",
+            "ADMINS LAPTOPS = (TARGETS) EDITORS"
+        ],
+    );
+    assert!(errors.is_empty());
+
+    let warnings = sudoers
+        .alias_warnings()
+        .into_iter()
+        .map(|Error { message, .. }| message)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        warnings,
+        [
+            "unused User_Alias 'UNUSED'",
+            "Host_Alias 'LAPTOPS' referenced but not defined",
+            "Cmnd_Alias 'EDITORS' referenced but not defined",
+            "Runas_Alias 'TARGETS' referenced but not defined",
+        ]
+    );
+}
+
+#[test]
+fn error_source_test() {
+    let (_, errors) = analyze(
+        Path::new("/etc/fakesudoers"),
+        parse_lines(&mut char_stream::PeekableWithPos::new(
+            "user ALL=ALL\nuser ALL=(ALL:ALL)\n".chars(),
+        )),
+    );
+    let [Error {
+        source,
+        location: Some(location),
+        ..
+    }] = &errors[..] else {
+        panic!("expected a single syntax error")
+    };
+    assert_eq!(source.as_deref(), Some(Path::new("/etc/fakesudoers")));
+    assert_eq!(location.start.0, 2);
 }
 
 #[test]
 fn default_bool_test() {
-    let (Sudoers { settings, .. }, _) = analyze(sudoer![
-        "Defaults env_reset",
-        "Defaults !use_pty",
-        "Defaults !env_keep",
-        "Defaults !umask",
-        "Defaults !secure_path"
-    ]);
+    let (Sudoers { settings, .. }, _) = analyze(
+        Path::new("/etc/fakesudoers"),
+        sudoer![
+            "Defaults env_reset",
+            "Defaults !use_pty",
+            "Defaults !env_keep",
+            "Defaults !umask",
+            "Defaults !secure_path"
+        ],
+    );
     assert!(settings.flags.contains("env_reset"));
     assert!(!settings.flags.contains("use_pty"));
     assert!(settings.list["env_keep"].is_empty());
@@ -242,17 +692,20 @@ fn default_bool_test() {
 
 #[test]
 fn default_set_test() {
-    let (Sudoers { settings, .. }, _) = analyze(sudoer![
-        "Defaults env_keep = \"FOO HUK BAR\"",
-        "Defaults env_keep -= HUK",
-        "Defaults !env_check",
-        "Defaults env_check += \"FOO\"",
-        "Defaults env_check += \"XYZZY\"",
-        "Defaults umask = 0123",
-        "Defaults passwd_tries = 5",
-        "Defaults lecture_file = \"/etc/sudoers\"",
-        "Defaults secure_path = /etc"
-    ]);
+    let (Sudoers { settings, .. }, _) = analyze(
+        Path::new("/etc/fakesudoers"),
+        sudoer![
+            "Defaults env_keep = \"FOO HUK BAR\"",
+            "Defaults env_keep -= HUK",
+            "Defaults !env_check",
+            "Defaults env_check += \"FOO\"",
+            "Defaults env_check += \"XYZZY\"",
+            "Defaults umask = 0123",
+            "Defaults passwd_tries = 5",
+            "Defaults lecture_file = \"/etc/sudoers\"",
+            "Defaults secure_path = /etc"
+        ],
+    );
     assert_eq!(
         settings.list["env_keep"],
         ["FOO", "BAR"].into_iter().map(|x| x.to_string()).collect()
@@ -279,9 +732,108 @@ fn default_set_test() {
     assert!(parse_string::<Sudo>("Defaults verifypw = never").is_ok());
 }
 
+#[test]
+fn authentication_settings_test() {
+    use crate::system::time::Duration;
+
+    let (sudoers, errors) = analyze(
+        Path::new("/etc/fakesudoers"),
+        sudoer![
+            "Defaults timestamp_timeout = 0.1",
+            "Defaults:user timestamp_timeout = -1, timestamp_type = global",
+            "Defaults:other !timestamp_timeout, timestamp_type = ppid",
+            "Defaults:other passwd_tries = 1, passwd_timeout = 0.5",
+            "Defaults:user !passwd_timeout",
+            "Defaults:user lecture = always, lecture_file = /etc/sudo_lecture",
+            "Defaults:other !lecture",
+            "Defaults:user passprompt = \"%p's password: \", passprompt_override",
+            "Defaults:user targetpw",
+            "Defaults:other targetpw, runaspw"
+        ],
+    );
+    assert!(errors.is_empty());
+
+    let auth = |user| sudoers.authentication(&Named(user), "server");
+    assert_eq!(
+        auth("root"),
+        Authentication {
+            credential: AuthenticatingUser::InvokingUser,
+            timestamp_timeout: Duration::seconds(6),
+            timestamp_type: TimestampType::Tty,
+            passwd_tries: 3,
+            passwd_timeout: Some(std::time::Duration::from_secs(300)),
+            lecture: Lecture::Once,
+            lecture_file: None,
+            passprompt: None,
+            passprompt_override: false,
+        }
+    );
+    assert_eq!(
+        auth("user"),
+        Authentication {
+            credential: AuthenticatingUser::TargetUser,
+            timestamp_timeout: Duration::seconds(-60),
+            timestamp_type: TimestampType::Global,
+            passwd_tries: 3,
+            passwd_timeout: None,
+            lecture: Lecture::Always,
+            lecture_file: Some("/etc/sudo_lecture".into()),
+            passprompt: Some("%p's password: ".to_string()),
+            passprompt_override: true,
+        }
+    );
+    assert_eq!(
+        auth("other"),
+        Authentication {
+            credential: AuthenticatingUser::Root,
+            timestamp_timeout: Duration::seconds(0),
+            timestamp_type: TimestampType::Ppid,
+            passwd_tries: 1,
+            passwd_timeout: Some(std::time::Duration::from_secs(30)),
+            lecture: Lecture::Never,
+            lecture_file: None,
+            passprompt: None,
+            passprompt_override: false,
+        }
+    );
+
+    assert!(parse_string::<Sudo>("Defaults timestamp_timeout = 1.2.3").is_err());
+    assert!(parse_string::<Sudo>("Defaults passwd_tries = -1").is_err());
+    assert!(parse_string::<Sudo>("Defaults timestamp_type = kernel").is_err());
+    assert!(parse_string::<Sudo>("Defaults lecture = sometimes").is_err());
+}
+
+#[test]
+fn default_env_value_test() {
+    let (Sudoers { settings, .. }, errors) = analyze(
+        Path::new("/etc/fakesudoers"),
+        sudoer![
+            "Defaults env_keep = \"FOO BAR=baz\"",
+            "Defaults env_keep += \"LC_*=en_* qux\"",
+            "Defaults env_check = \"TZ=Europe/*\""
+        ],
+    );
+    assert!(errors.is_empty());
+    assert_eq!(
+        settings.list["env_keep"],
+        ["FOO", "BAR=baz", "LC_*=en_*", "qux"]
+            .into_iter()
+            .map(|x| x.to_string())
+            .collect()
+    );
+    assert_eq!(
+        settings.list["env_check"],
+        ["TZ=Europe/*".to_string()].into_iter().collect()
+    );
+
+    assert!(parse_string::<Sudo>("Defaults env_keep = \"FOO=\"").is_err());
+    assert!(parse_string::<Sudo>("Defaults env_keep = \"FOO=bar\" BAZ").is_err());
+    assert!(parse_string::<Sudo>("Defaults env_keep = FOO=bar").is_err());
+}
+
 #[test]
 fn default_multi_test() {
-    let (Sudoers { settings, .. }, _) = analyze(sudoer![
+    let (Sudoers { settings, .. }, _) = analyze(Path::new("/etc/fakesudoers"), sudoer![
         "Defaults env_reset, umask = 0123, secure_path=/etc, env_keep = \"FOO BAR\", env_keep -= BAR"
     ]);
     assert!(settings.flags.contains("env_reset"));
@@ -293,6 +845,110 @@ fn default_multi_test() {
     );
 }
 
+#[test]
+fn pre_judgement_scope_test() {
+    let (sudoers, errors) = analyze(
+        Path::new("/etc/fakesudoers"),
+        sudoer![
+            "Defaults secure_path = /bin, editor = /bin/ed",
+            "Defaults:user secure_path = /usr/bin, !env_editor",
+            "Defaults@server editor = /usr/bin/vi",
+            "Defaults!/bin/ls secure_path = /sbin"
+        ],
+    );
+    assert!(errors.is_empty());
+
+    let secure_path = |user, host| sudoers.secure_path(&Named(user), host);
+    assert_eq!(secure_path("other", "laptop").as_deref(), Some("/bin"));
+    assert_eq!(secure_path("user", "laptop").as_deref(), Some("/usr/bin"));
+
+    assert_eq!(sudoers.editor(&Named("user"), "laptop"), "/bin/ed");
+    assert_eq!(sudoers.editor(&Named("user"), "server"), "/usr/bin/vi");
+    assert!(sudoers.env_editor(&Named("other"), "laptop"));
+    assert!(!sudoers.env_editor(&Named("user"), "laptop"));
+}
+
+#[test]
+fn default_scope_test() {
+    let (sudoers, errors) = analyze(
+        Path::new("/etc/fakesudoers"),
+        sudoer![
+            "Defaults!/bin/ls umask = 0111",
+            "Defaults>root umask = 0222, passwd_tries = 4",
+            "Defaults:user umask = 0333, !env_reset",
+            "Defaults@server umask = 0444, passwd_tries = 2",
+            "Defaults:OTHERS,!user verifypw = never",
+            "Defaults!SHELLS umask = 0555",
+            "User_Alias OTHERS = other",
+            "Cmnd_Alias SHELLS = /bin/sh, /bin/bash",
+            "Defaults umask = 0666",
+            "ALL ALL=(ALL:ALL) ALL"
+        ],
+    );
+    assert!(errors.is_empty());
+
+    let settings = |user, host, target, command: &str| {
+        let target = Named(target);
+        let req = Request {
+            user: &target,
+            group: &target,
+            command: command.as_ref(),
+            arguments: &[],
+        };
+        sudoers.check(&Named(user), host, req).settings
+    };
+
+    let generic = settings("other", "laptop", "other", "/bin/true");
+    assert_eq!(generic.int_value["umask"], 0o666);
+    assert_eq!(generic.int_value["passwd_tries"], 3);
+    assert!(generic.flags.contains("env_reset"));
+
+    // host, user, runas and command defaults are applied in that order
+    let scoped = settings("user", "server", "root", "/bin/ls");
+    assert_eq!(scoped.int_value["umask"], 0o111);
+    assert_eq!(scoped.int_value["passwd_tries"], 4);
+    assert!(!scoped.flags.contains("env_reset"));
+    assert_eq!(
+        settings("user", "server", "root", "/bin/cat").int_value["umask"],
+        0o222
+    );
+    assert_eq!(
+        settings("user", "server", "user", "/bin/cat").int_value["umask"],
+        0o333
+    );
+    assert_eq!(
+        settings("other", "server", "user", "/bin/cat").int_value["umask"],
+        0o444
+    );
+    assert_eq!(
+        settings("other", "laptop", "root", "/bin/bash").int_value["umask"],
+        0o555
+    );
+
+    // without a command, only host and user defaults apply
+    let validate = |user| sudoers.check_validate_permission(&Named(user), "server");
+    assert_eq!(
+        validate("other"),
+        Authorization::Allowed {
+            must_authenticate: false
+        }
+    );
+    assert_eq!(
+        validate("user"),
+        Authorization::Allowed {
+            must_authenticate: true
+        }
+    );
+
+    // a scope must directly follow the keyword
+    let Sudo::Decl(Directive::Defaults(_, scope)) = parse_line("Defaults !lecture") else { panic!() };
+    let ConfigScope::Generic = scope else { panic!() };
+    let Sudo::Decl(Directive::Defaults(_, scope)) = parse_line("Defaults!/bin/ls,!/bin/cat* !lecture") else { panic!() };
+    let ConfigScope::Command(commands) = scope else { panic!() };
+    assert_eq!(commands.len(), 2);
+    assert!(parse_string::<Sudo>("Defaults!/bin/ls -l !lecture").is_err());
+}
+
 #[test]
 #[should_panic]
 fn invalid_directive() {
@@ -36,6 +36,8 @@ impl Token for Digits {
     }
 }
 
+/// A numeric value of a setting; this can be negative or have a fractional part, it is up to
+/// the setting to decide whether that is allowed
 pub struct Numeric(pub String);
 
 impl Token for Numeric {
@@ -46,11 +48,12 @@ impl Token for Numeric {
     }
 
     fn accept(c: char) -> bool {
-        c.is_ascii_hexdigit()
+        c.is_ascii_hexdigit() || c == '-' || c == '.'
     }
 }
 
 /// A hostname consists of alphanumeric characters and ".", "-",  "_"
+#[cfg_attr(test, derive(Clone, Debug, PartialEq, Eq))]
 pub struct Hostname(pub String);
 
 impl std::ops::Deref for Hostname {
@@ -133,9 +136,9 @@ impl Token for AliasName {
 
 /// A struct that represents valid command strings; this can contain escape sequences and are
 /// limited to 1024 characters.
-pub type Command = (glob::Pattern, Option<Box<[String]>>);
+pub type SimpleCommand = (glob::Pattern, Option<Box<[String]>>);
 
-impl Token for Command {
+impl Token for SimpleCommand {
     const MAX_LEN: usize = 1024;
 
     fn construct(s: String) -> Result<Self, String> {
@@ -177,10 +180,47 @@ impl Token for Command {
     }
 }
 
-impl Many for Command {}
+/// A command in the scope of a `Defaults!` directive; unlike in a [SimpleCommand], whitespace ends
+/// the command, so these cannot have arguments.
+pub struct DefaultsCommand(pub SimpleCommand);
+
+impl Token for DefaultsCommand {
+    const MAX_LEN: usize = SimpleCommand::MAX_LEN;
+
+    fn construct(s: String) -> Result<Self, String> {
+        SimpleCommand::construct(s).map(DefaultsCommand)
+    }
+
+    fn accept_1st(c: char) -> bool {
+        SimpleCommand::accept_1st(c)
+    }
+
+    fn accept(c: char) -> bool {
+        SimpleCommand::accept(c) && !c.is_whitespace()
+    }
+
+    const ESCAPE: char = SimpleCommand::ESCAPE;
+    fn escaped(c: char) -> bool {
+        SimpleCommand::escaped(c)
+    }
+}
+
+impl Many for DefaultsCommand {}
+
+/// The value of a digest, in hexadecimal or base64 notation
+pub struct DigestValue(pub String);
+
+impl Token for DigestValue {
+    fn construct(s: String) -> Result<Self, String> {
+        Ok(DigestValue(s))
+    }
+
+    fn accept(c: char) -> bool {
+        c.is_ascii_alphanumeric() || "+/=".contains(c)
+    }
+}
 
 /// An environment variable name pattern consists of alphanumeric characters as well as "_", "%" and wildcard "*"
-/// (Value patterns are not supported yet)
 pub struct EnvVar(pub String);
 
 impl Token for EnvVar {
@@ -193,6 +233,27 @@ impl Token for EnvVar {
     }
 }
 
+/// The value pattern of an environment variable in a quoted list, e.g. `en_*` in
+/// `env_keep = "LC_*=en_* TZ"`; it ends at whitespace or at the closing quote.
+pub struct EnvValue(pub String);
+
+impl Token for EnvValue {
+    const MAX_LEN: usize = QuotedText::MAX_LEN;
+
+    fn construct(s: String) -> Result<Self, String> {
+        Ok(EnvValue(s))
+    }
+
+    fn accept(c: char) -> bool {
+        !c.is_whitespace() && !Self::escaped(c)
+    }
+
+    const ESCAPE: char = '\\';
+    fn escaped(c: char) -> bool {
+        "\\\"".contains(c) || c.is_control()
+    }
+}
+
 pub struct QuotedText(pub String);
 
 impl Token for QuotedText {
@@ -252,8 +313,8 @@ impl Token for StringParameter {
 }
 
 // a path used for in CWD and CHROOT specs
-#[derive(Clone)]
-#[cfg_attr(test, derive(Debug, PartialEq, Eq))]
+#[derive(Clone, PartialEq, Eq)]
+#[cfg_attr(test, derive(Debug))]
 pub enum ChDir {
     Path(std::path::PathBuf),
     Any,
@@ -1,4 +1,9 @@
-use std::{fs::File, io::Result, os::fd::AsRawFd};
+use std::{
+    fs::File,
+    io::{self, Result},
+    os::fd::AsRawFd,
+    path::Path,
+};
 
 use crate::cutils::cerr;
 
@@ -7,6 +12,10 @@ pub trait Lockable {
     /// on the file
     fn lock_exclusive(&self) -> Result<()>;
 
+    /// Get an exclusive lock on the file, fails with `WouldBlock` if there is
+    /// currently a lock on the file
+    fn try_lock_exclusive(&self) -> Result<()>;
+
     /// Get a shared lock on the file, waits if there is currently an exclusive
     /// lock on the file.
     fn lock_shared(&self) -> Result<()>;
@@ -47,6 +56,10 @@ impl Lockable for File {
         flock(self, LockOp::LockExclusive, true)
     }
 
+    fn try_lock_exclusive(&self) -> Result<()> {
+        flock(self, LockOp::LockExclusive, false)
+    }
+
     fn lock_shared(&self) -> Result<()> {
         flock(self, LockOp::LockShared, true)
     }
@@ -56,6 +69,11 @@ impl Lockable for File {
     }
 }
 
+/// Prefix an I/O error with the path of the file it occurred on, keeping its kind
+pub fn file_error(path: &Path, err: io::Error) -> io::Error {
+    io::Error::new(err.kind(), format!("{}: {err}", path.display()))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -65,6 +83,10 @@ mod tests {
             Ok(())
         }
 
+        fn try_lock_exclusive(&self) -> Result<()> {
+            Ok(())
+        }
+
         fn lock_shared(&self) -> Result<()> {
             Ok(())
         }
@@ -79,6 +101,10 @@ mod tests {
             Ok(())
         }
 
+        fn try_lock_exclusive(&self) -> Result<()> {
+            Ok(())
+        }
+
         fn lock_shared(&self) -> Result<()> {
             Ok(())
         }
@@ -96,4 +122,15 @@ mod tests {
         assert!(f.lock_exclusive().is_ok());
         assert!(f.unlock().is_ok());
     }
+
+    #[test]
+    fn test_try_locking_of_locked_file() {
+        let f = tempfile::NamedTempFile::new().unwrap();
+        let other = File::open(f.path()).unwrap();
+        assert!(f.as_file().lock_exclusive().is_ok());
+        let err = other.try_lock_exclusive().unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::WouldBlock);
+        assert!(f.as_file().unlock().is_ok());
+        assert!(other.try_lock_exclusive().is_ok());
+    }
 }
@@ -0,0 +1,94 @@
+//! Non-Unix groups (`%:group` in the sudoers file) are resolved by a group provider; this is the
+//! equivalent of the `group_plugin` of the original sudo.
+
+use std::collections::HashMap;
+use std::io::{self, Read};
+use std::path::Path;
+
+use super::secure_open;
+
+/// A source of group memberships other than the groups of the system
+pub trait GroupProvider {
+    /// Check whether the user named `user` is a member of `group`, which is either a group name
+    /// or a numeric group ID preceded by `#`.
+    fn is_member(&self, user: &str, group: &str) -> bool;
+}
+
+/// Groups read from a file in the format of `/etc/group`, like the `group_file` plugin of the
+/// original sudo:
+/// ```text
+/// admins:*:2000:alice,bob
+/// ```
+#[derive(Debug, Default)]
+pub struct GroupFile {
+    members: HashMap<String, Vec<String>>,
+}
+
+impl GroupFile {
+    /// The name under which this provider can be selected with the `group_plugin` setting
+    pub const PLUGIN_NAME: &str = "group_file.so";
+
+    pub fn open(path: impl AsRef<Path>) -> io::Result<GroupFile> {
+        let mut text = String::new();
+        secure_open(path)?.read_to_string(&mut text)?;
+
+        Ok(Self::parse(&text))
+    }
+
+    pub fn parse(text: &str) -> GroupFile {
+        let mut members = HashMap::new();
+
+        for line in text.lines() {
+            if line.starts_with('#') {
+                continue;
+            }
+            let fields = line.split(':').collect::<Vec<_>>();
+            let [name, _password, gid, users] = fields[..] else {
+                continue;
+            };
+            let users = users
+                .split(',')
+                .map(str::trim)
+                .filter(|user| !user.is_empty())
+                .map(str::to_string)
+                .collect::<Vec<_>>();
+
+            // a group can be referred to both by its name and by its ID
+            members.insert(format!("#{gid}"), users.clone());
+            members.insert(name.to_string(), users);
+        }
+
+        GroupFile { members }
+    }
+}
+
+impl GroupProvider for GroupFile {
+    fn is_member(&self, user: &str, group: &str) -> bool {
+        self.members
+            .get(group)
+            .is_some_and(|members| members.iter().any(|member| member == user))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_group_file() {
+        let groups = GroupFile::parse(
+            "# comment\n\
+             admins:*:2000:alice, bob\n\
+             empty:x:2001:\n\
+             malformed line\n",
+        );
+
+        assert!(groups.is_member("alice", "admins"));
+        assert!(groups.is_member("bob", "admins"));
+        assert!(groups.is_member("bob", "#2000"));
+        assert!(!groups.is_member("carol", "admins"));
+        assert!(!groups.is_member("alice", "empty"));
+        assert!(!groups.is_member("alice", "#2001"));
+        assert!(!groups.is_member("alice", "malformed line"));
+    }
+}
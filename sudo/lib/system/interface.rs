@@ -22,6 +22,9 @@ pub trait UnixUser {
     fn in_group_by_gid(&self, _gid: GroupId) -> bool {
         false
     }
+    fn try_as_name(&self) -> Option<&str> {
+        None
+    }
 }
 
 pub trait UnixGroup {
@@ -49,6 +52,9 @@ impl UnixUser for super::User {
     fn in_group_by_gid(&self, gid: GroupId) -> bool {
         self.groups.contains(&gid)
     }
+    fn try_as_name(&self) -> Option<&str> {
+        Some(&self.name)
+    }
 }
 
 impl UnixGroup for super::Group {
@@ -71,6 +77,7 @@ mod test {
         assert!(user.has_name(name));
         assert!(user.has_uid(uid));
         assert!(user.in_group_by_name(name));
+        assert_eq!(user.try_as_name(), Some(name));
         assert_eq!(user.is_root(), name == "root");
     }
 
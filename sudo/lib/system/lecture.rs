@@ -0,0 +1,58 @@
+use std::{
+    io::{self, Read},
+    path::{Path, PathBuf},
+};
+
+use crate::log::auth_warn;
+
+use super::audit::{secure_open, secure_open_cookie_file};
+
+const STANDARD_LECTURE: &str = "
+We trust you have received the usual lecture from the local System
+Administrator. It usually boils down to these three things:
+
+    #1) Respect the privacy of others.
+    #2) Think before you type.
+    #3) With great power comes great responsibility.
+
+";
+
+/// The directory in which an empty file is created for every user that has
+/// been lectured. This is not a tmpfs, so the status survives a reboot.
+const BASE_PATH: &str = "/var/lib/sudo-rs/lectured";
+
+fn status_path(user: &str) -> PathBuf {
+    let mut path = PathBuf::from(BASE_PATH);
+    path.push(user);
+    path
+}
+
+fn read_lecture_file(path: &Path) -> io::Result<String> {
+    let mut text = String::new();
+    secure_open(path)?.read_to_string(&mut text)?;
+    Ok(text)
+}
+
+/// Returns the text of the lecture: the contents of `lecture_file` if it is
+/// given and can be read, or the standard lecture otherwise.
+pub fn lecture_text(lecture_file: Option<&Path>) -> String {
+    if let Some(path) = lecture_file {
+        match read_lecture_file(path) {
+            Ok(text) => return text,
+            Err(e) => auth_warn!("Could not read lecture file {}: {e}", path.display()),
+        }
+    }
+
+    STANDARD_LECTURE.to_string()
+}
+
+/// Check if the user has been lectured before; only a status file owned by
+/// root is taken into account.
+pub fn already_lectured(user: &str) -> bool {
+    secure_open(status_path(user)).is_ok()
+}
+
+/// Record that the user has been lectured, creating the status directory if needed.
+pub fn set_lectured(user: &str) -> io::Result<()> {
+    secure_open_cookie_file(status_path(user)).map(|_| ())
+}
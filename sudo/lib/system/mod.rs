@@ -20,10 +20,16 @@ pub mod interface;
 
 pub mod file;
 
+pub mod group_plugin;
+
+pub mod netgroup;
+
 pub mod time;
 
 pub mod timestamp;
 
+pub mod lecture;
+
 pub mod signal;
 
 pub mod poll;
@@ -115,6 +121,54 @@ pub fn set_target_user(
     }
 }
 
+/// Run `action` with the effective user, group and supplementary groups of `user` (with `gid` as
+/// the effective group); the original credentials are restored afterwards. This allows accessing
+/// files with the permissions of that user, which requires the process to be running as root.
+pub fn with_effective_user<T>(
+    user: &User,
+    gid: GroupId,
+    action: impl FnOnce() -> T,
+) -> io::Result<T> {
+    let saved_uid = User::effective_uid();
+    let saved_gid = Group::effective_gid();
+    let saved_groups = getgroups()?;
+
+    let restore = || {
+        cerr(unsafe { libc::seteuid(saved_uid) })?;
+        cerr(unsafe { libc::setegid(saved_gid) })?;
+        cerr(unsafe { libc::setgroups(saved_groups.len(), saved_groups.as_ptr()) })?;
+        io::Result::Ok(())
+    };
+
+    let mut groups = user.groups.clone();
+    if !groups.contains(&gid) {
+        groups.push(gid);
+    }
+
+    let switched = (|| {
+        cerr(unsafe { libc::setgroups(groups.len(), groups.as_ptr()) })?;
+        cerr(unsafe { libc::setegid(gid) })?;
+        cerr(unsafe { libc::seteuid(user.uid) })?;
+        io::Result::Ok(())
+    })();
+
+    let result = switched.map(|()| action());
+
+    // continuing with the wrong credentials would be a security problem
+    restore().expect("could not restore process credentials");
+
+    result
+}
+
+fn getgroups() -> io::Result<Vec<GroupId>> {
+    let count = cerr(unsafe { libc::getgroups(0, std::ptr::null_mut()) })?;
+    let mut groups = vec![0; count as usize];
+    let count = cerr(unsafe { libc::getgroups(count, groups.as_mut_ptr()) })?;
+    groups.truncate(count as usize);
+
+    Ok(groups)
+}
+
 /// Send a signal to a process with the specified ID.
 pub fn kill(pid: ProcessId, signal: c_int) -> io::Result<()> {
     // SAFETY: This function cannot cause UB even if `pid` is not a valid process ID or if
@@ -144,6 +198,12 @@ pub fn chdir<S: AsRef<CStr>>(path: &S) -> io::Result<()> {
     cerr(unsafe { libc::chdir(path.as_ref().as_ptr()) }).map(|_| ())
 }
 
+/// Change the owner and group of an open file.
+pub fn fchown(fd: &impl AsRawFd, uid: UserId, gid: GroupId) -> io::Result<()> {
+    // SAFETY: This function cannot cause UB even if `fd` is not a valid file descriptor.
+    cerr(unsafe { libc::fchown(fd.as_raw_fd(), uid, gid) }).map(|_| ())
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct User {
     pub uid: UserId,
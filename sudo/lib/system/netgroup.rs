@@ -0,0 +1,216 @@
+//! Netgroups are sets of (host, user, domain) triples, usually defined in NIS or in
+//! `/etc/netgroup`. The sudoers policy can refer to them as `+netgroup` in user and host lists.
+
+use std::collections::{HashMap, HashSet};
+use std::ffi::{c_char, c_int, CString};
+use std::ptr;
+
+/// A source of netgroup information.
+pub trait NetgroupProvider {
+    /// Check whether `netgroup` contains a triple that matches `host` and `user`; an argument
+    /// that is `None` is not checked, just like a null argument to `innetgr(3)`.
+    fn contains(&self, netgroup: &str, host: Option<&str>, user: Option<&str>) -> bool;
+}
+
+extern "C" {
+    fn innetgr(
+        netgroup: *const c_char,
+        host: *const c_char,
+        user: *const c_char,
+        domain: *const c_char,
+    ) -> c_int;
+}
+
+/// Look up netgroups using the C library, which consults the sources configured in
+/// `/etc/nsswitch.conf` (such as NIS or `/etc/netgroup`).
+#[derive(Default)]
+pub struct SystemNetgroups;
+
+impl NetgroupProvider for SystemNetgroups {
+    fn contains(&self, netgroup: &str, host: Option<&str>, user: Option<&str>) -> bool {
+        let to_cstring = |text: Option<&str>| text.map(CString::new).transpose();
+        let (Ok(netgroup), Ok(host), Ok(user)) = (
+            CString::new(netgroup),
+            to_cstring(host),
+            to_cstring(user),
+        ) else {
+            return false;
+        };
+        let as_ptr = |text: &Option<CString>| text.as_ref().map_or(ptr::null(), |s| s.as_ptr());
+
+        // SAFETY: all arguments are either null or valid null-terminated strings
+        unsafe { innetgr(netgroup.as_ptr(), as_ptr(&host), as_ptr(&user), ptr::null()) == 1 }
+    }
+}
+
+/// A field of a netgroup triple; an empty field is a wildcard, and a field consisting of a
+/// single dash never matches.
+#[derive(Debug, PartialEq)]
+enum Field {
+    Any,
+    Nothing,
+    Value(String),
+}
+
+impl Field {
+    fn new(text: &str) -> Field {
+        match text.trim() {
+            "" => Field::Any,
+            "-" => Field::Nothing,
+            value => Field::Value(value.to_string()),
+        }
+    }
+
+    fn matches(&self, query: Option<&str>) -> bool {
+        match (self, query) {
+            (_, None) | (Field::Any, _) => true,
+            (Field::Nothing, _) => false,
+            (Field::Value(value), Some(query)) => value == query,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Member {
+    Triple { host: Field, user: Field },
+    Netgroup(String),
+}
+
+/// Netgroups given in the format of `netgroup(5)`, so that they can be looked up without
+/// consulting NIS or the system files:
+/// ```text
+/// admins (server1,alice,) (,bob,example.com) \
+///        operators
+/// ```
+#[derive(Debug, Default)]
+pub struct NetgroupFile {
+    netgroups: HashMap<String, Vec<Member>>,
+}
+
+impl NetgroupFile {
+    pub fn parse(text: &str) -> NetgroupFile {
+        let mut netgroups = HashMap::new();
+
+        let text = text.replace("\\\n", " ");
+        for line in text.lines() {
+            let line = line.split_once('#').map_or(line, |(line, _comment)| line);
+            let Some((name, mut rest)) = line.trim().split_once(char::is_whitespace) else {
+                continue;
+            };
+
+            let mut members = Vec::new();
+            loop {
+                rest = rest.trim_start();
+                if let Some(triple) = rest.strip_prefix('(') {
+                    let Some((triple, remainder)) = triple.split_once(')') else { break };
+                    let mut fields = triple.split(',');
+                    members.push(Member::Triple {
+                        host: Field::new(fields.next().unwrap_or_default()),
+                        user: Field::new(fields.next().unwrap_or_default()),
+                    });
+                    rest = remainder;
+                } else if let Some(netgroup) = rest.split_whitespace().next() {
+                    members.push(Member::Netgroup(netgroup.to_string()));
+                    rest = &rest[netgroup.len()..];
+                } else {
+                    break;
+                }
+            }
+
+            netgroups.insert(name.to_string(), members);
+        }
+
+        NetgroupFile { netgroups }
+    }
+
+    fn contains_rec<'a>(
+        &'a self,
+        netgroup: &'a str,
+        host: Option<&str>,
+        user: Option<&str>,
+        seen: &mut HashSet<&'a str>,
+    ) -> bool {
+        // netgroups can be nested, and the nesting may contain cycles
+        if !seen.insert(netgroup) {
+            return false;
+        }
+
+        self.netgroups.get(netgroup).is_some_and(|members| {
+            members.iter().any(|member| match member {
+                Member::Triple {
+                    host: host_field,
+                    user: user_field,
+                } => host_field.matches(host) && user_field.matches(user),
+                Member::Netgroup(name) => self.contains_rec(name, host, user, seen),
+            })
+        })
+    }
+}
+
+impl NetgroupProvider for NetgroupFile {
+    fn contains(&self, netgroup: &str, host: Option<&str>, user: Option<&str>) -> bool {
+        self.contains_rec(netgroup, host, user, &mut HashSet::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NETGROUPS: &str = "\
+# the administrators
+admins (server1,alice,) (,bob,example.com) \\
+       operators
+operators ( server2 , carol , )
+nobody (-,-,)
+loop1 loop2
+loop2 loop1 (,dave,)
+";
+
+    #[test]
+    fn parse_netgroup_file() {
+        let file = NetgroupFile::parse(NETGROUPS);
+        assert_eq!(
+            file.netgroups["admins"],
+            [
+                Member::Triple {
+                    host: Field::Value("server1".to_string()),
+                    user: Field::Value("alice".to_string()),
+                },
+                Member::Triple {
+                    host: Field::Any,
+                    user: Field::Value("bob".to_string()),
+                },
+                Member::Netgroup("operators".to_string()),
+            ]
+        );
+        assert_eq!(file.netgroups.len(), 5);
+    }
+
+    #[test]
+    fn match_users_and_hosts() {
+        let file = NetgroupFile::parse(NETGROUPS);
+        assert!(file.contains("admins", None, Some("alice")));
+        assert!(file.contains("admins", Some("server1"), Some("alice")));
+        assert!(!file.contains("admins", Some("server2"), Some("alice")));
+        assert!(file.contains("admins", Some("anything"), Some("bob")));
+        assert!(file.contains("admins", None, Some("carol")));
+        assert!(file.contains("admins", Some("server2"), None));
+        assert!(!file.contains("admins", None, Some("eve")));
+        assert!(!file.contains("nobody", None, Some("alice")));
+        assert!(!file.contains("nobody", Some("server1"), None));
+        assert!(!file.contains("unknown", None, None));
+    }
+
+    #[test]
+    fn nested_netgroups_with_cycle() {
+        let file = NetgroupFile::parse(NETGROUPS);
+        assert!(file.contains("loop1", None, Some("dave")));
+        assert!(!file.contains("loop1", None, Some("alice")));
+    }
+
+    #[test]
+    fn system_netgroups_without_configuration() {
+        assert!(!SystemNetgroups.contains("sudo-rs-no-such-netgroup", None, Some("root")));
+    }
+}
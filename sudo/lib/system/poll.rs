@@ -3,10 +3,11 @@ use std::{
     hash::Hash,
     io,
     os::fd::{AsRawFd, RawFd},
+    time::Duration,
 };
 
 use crate::cutils::cerr;
-use libc::{c_short, pollfd, POLLIN, POLLOUT};
+use libc::{c_int, c_short, pollfd, POLLIN, POLLOUT};
 
 /// A set of indexed file descriptors to be polled using the [`poll`](https://manpage.me/?q=poll) system call.
 pub struct PollSet<K> {
@@ -54,6 +55,13 @@ impl<K: Eq + PartialEq + Hash + Clone> PollSet<K> {
     ///
     /// Calling this function will block until one of the file descriptors in the set is ready.
     pub fn poll(&mut self) -> io::Result<Vec<K>> {
+        // FIXME: we should set either a timeout or use ppoll when available.
+        self.poll_timeout(None)
+    }
+
+    /// Like [`poll`](Self::poll), but stop waiting when `timeout` has passed, in which case no
+    /// keys are returned.
+    pub fn poll_timeout(&mut self, timeout: Option<Duration>) -> io::Result<Vec<K>> {
         let mut fds: Vec<pollfd> = self
             .fds
             .values()
@@ -64,8 +72,10 @@ impl<K: Eq + PartialEq + Hash + Clone> PollSet<K> {
             })
             .collect();
 
-        // FIXME: we should set either a timeout or use ppoll when available.
-        let n = cerr(unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as _, -1) })?;
+        let timeout = timeout.map_or(-1, |timeout| {
+            timeout.as_millis().min(c_int::MAX as u128) as c_int
+        });
+        let n = cerr(unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as _, timeout) })?;
 
         let mut keys = Vec::with_capacity(n as usize);
 
@@ -1,4 +1,5 @@
 use std::{
+    cmp::Ordering,
     fs::File,
     io::{self, Cursor, Read, Seek, Write},
     path::PathBuf,
@@ -51,7 +52,7 @@ impl<'u> SessionRecordFile<'u, File> {
 }
 
 impl<'u, IO: Read + Write + Seek + SetLength + Lockable> SessionRecordFile<'u, IO> {
-    const FILE_VERSION: u16 = 1;
+    const FILE_VERSION: u16 = 2;
     const MAGIC_NUM: u16 = 0x50D0;
     const VERSION_OFFSET: u64 = Self::MAGIC_NUM.to_le_bytes().len() as u64;
     const FIRST_RECORD_OFFSET: u64 =
@@ -59,7 +60,8 @@ impl<'u, IO: Read + Write + Seek + SetLength + Lockable> SessionRecordFile<'u, I
 
     /// Create a new SessionRecordFile from the given i/o stream.
     /// Timestamps in this file are considered valid if they were created or
-    /// updated at most `timeout` time ago.
+    /// updated at most `timeout` time ago; if `timeout` is zero, they are
+    /// never valid, and if it is negative, they are always valid.
     pub fn new(for_user: &'u str, io: IO, timeout: Duration) -> io::Result<SessionRecordFile<IO>> {
         let mut session_records = SessionRecordFile {
             io,
@@ -199,7 +201,13 @@ impl<'u, IO: Read + Write + Seek + SetLength + Lockable> SessionRecordFile<'u, I
             // only touch if record is enabled
             if record.enabled && record.matches(&scope, auth_user) {
                 let now = SystemTime::now()?;
-                if record.written_between(now - self.timeout, now) {
+                let valid = match self.timeout.cmp(&Duration::seconds(0)) {
+                    // a zero timeout means that a record is never valid
+                    Ordering::Equal => false,
+                    Ordering::Less => record.timestamp <= now,
+                    Ordering::Greater => record.written_between(now - self.timeout, now),
+                };
+                if valid {
                     // move back to where the timestamp is and overwrite with the latest time
                     self.io.seek(io::SeekFrom::Current(-MOD_OFFSET))?;
                     let new_time = SystemTime::now()?;
@@ -348,6 +356,7 @@ pub enum RecordScope {
         group_pid: libc::pid_t,
         init_time: SystemTime,
     },
+    Global,
 }
 
 impl RecordScope {
@@ -374,6 +383,9 @@ impl RecordScope {
                 target.write_all(&b)?;
                 init_time.encode(target)?;
             }
+            RecordScope::Global => {
+                target.write_all(&[3u8])?;
+            }
         }
 
         Ok(())
@@ -407,6 +419,7 @@ impl RecordScope {
                     init_time,
                 })
             }
+            3 => Ok(RecordScope::Global),
             x => Err(io::Error::new(
                 io::ErrorKind::InvalidInput,
                 format!("Unexpected scope variant discriminator: {x}"),
@@ -601,6 +614,11 @@ mod tests {
         let bytes = ppid_sample.as_bytes().unwrap();
         let decoded = SessionRecord::from_bytes(&bytes).unwrap();
         assert_eq!(ppid_sample, decoded);
+
+        let global_sample = SessionRecord::new(RecordScope::Global, 456).unwrap();
+        let bytes = global_sample.as_bytes().unwrap();
+        let decoded = SessionRecord::from_bytes(&bytes).unwrap();
+        assert_eq!(global_sample, decoded);
     }
 
     #[test]
@@ -667,29 +685,29 @@ mod tests {
     #[test]
     fn session_record_file_header_checks() {
         // valid header should remain valid
-        let mut v = vec![0xD0, 0x50, 0x01, 0x00];
+        let mut v = vec![0xD0, 0x50, 0x02, 0x00];
         let c = Cursor::new(&mut v);
         let timeout = Duration::seconds(30);
         assert!(SessionRecordFile::new("test", c, timeout).is_ok());
-        assert_eq!(&v[..], &[0xD0, 0x50, 0x01, 0x00]);
+        assert_eq!(&v[..], &[0xD0, 0x50, 0x02, 0x00]);
 
         // invalid headers should be corrected
         let mut v = vec![0xAB, 0xBA];
         let c = Cursor::new(&mut v);
         assert!(SessionRecordFile::new("test", c, timeout).is_ok());
-        assert_eq!(&v[..], &[0xD0, 0x50, 0x01, 0x00]);
+        assert_eq!(&v[..], &[0xD0, 0x50, 0x02, 0x00]);
 
         // empty header should be filled in
         let mut v = vec![];
         let c = Cursor::new(&mut v);
         assert!(SessionRecordFile::new("test", c, timeout).is_ok());
-        assert_eq!(&v[..], &[0xD0, 0x50, 0x01, 0x00]);
+        assert_eq!(&v[..], &[0xD0, 0x50, 0x02, 0x00]);
 
         // invalid version should reset file
         let mut v = vec![0xD0, 0x50, 0xAB, 0xBA, 0x0, 0x0];
         let c = Cursor::new(&mut v);
         assert!(SessionRecordFile::new("test", c, timeout).is_ok());
-        assert_eq!(&v[..], &[0xD0, 0x50, 0x01, 0x00]);
+        assert_eq!(&v[..], &[0xD0, 0x50, 0x02, 0x00]);
     }
 
     #[test]
@@ -727,6 +745,58 @@ mod tests {
         assert!(srf.reset().is_ok());
 
         // after all this the data should be just an empty header
-        assert_eq!(&data, &[0xD0, 0x50, 0x01, 0x00]);
+        assert_eq!(&data, &[0xD0, 0x50, 0x02, 0x00]);
+    }
+
+    #[test]
+    fn create_refreshes_disabled_and_outdated_records() {
+        let mut data = vec![];
+        let scope = RecordScope::Global;
+        let auth_user = 2424;
+
+        // a record that is no longer valid is brought back without authenticating again
+        let c = Cursor::new(&mut data);
+        let mut srf = SessionRecordFile::new("test", c, Duration::seconds(0)).unwrap();
+        srf.create(scope, auth_user).unwrap();
+        let CreateResult::Updated { .. } = srf.create(scope, auth_user).unwrap() else {
+            panic!("Expected record to be updated");
+        };
+
+        // a record that was disabled with -k is enabled again
+        let c = Cursor::new(&mut data);
+        let mut srf = SessionRecordFile::new("test", c, Duration::seconds(30)).unwrap();
+        srf.disable(scope, None).unwrap();
+        let TouchResult::NotFound = srf.touch(scope, auth_user).unwrap() else {
+            panic!("Expected disabled record to be ignored");
+        };
+        srf.create(scope, auth_user).unwrap();
+        let TouchResult::Updated { .. } = srf.touch(scope, auth_user).unwrap() else {
+            panic!("Expected record to be updated");
+        };
+    }
+
+    #[test]
+    fn timeout_determines_validity() {
+        let mut data = vec![];
+        let scope = RecordScope::Global;
+        let auth_user = 2424;
+
+        // a zero timeout means that a record is never valid
+        let c = Cursor::new(&mut data);
+        let mut srf = SessionRecordFile::new("test", c, Duration::seconds(0)).unwrap();
+        srf.create(scope, auth_user).unwrap();
+        let TouchResult::Outdated { .. } = srf.touch(scope, auth_user).unwrap() else {
+            panic!("Expected record to be outdated");
+        };
+
+        // a negative timeout means that a record never expires
+        let c = Cursor::new(&mut data);
+        let mut srf = SessionRecordFile::new("test", c, Duration::seconds(-1)).unwrap();
+        let TouchResult::Updated { .. } = srf.touch(scope, auth_user).unwrap() else {
+            panic!("Expected record to be updated");
+        };
+        let TouchResult::NotFound = srf.touch(scope, 1234).unwrap() else {
+            panic!("Expected no record for another user");
+        };
     }
 }
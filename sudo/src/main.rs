@@ -3,12 +3,15 @@
 use pam::{determine_record_scope, PamAuthenticator};
 use pipeline::{Pipeline, PolicyPlugin};
 use std::env;
+use std::path::PathBuf;
 use sudo::cli::{help, SudoAction, SudoOptions};
 use sudo::common::{resolve::resolve_current_user, Context, Error};
+use sudo::config::{SudoConf, SUDO_CONF_PATH};
+use sudo::diagnostic::diagnostic;
+use sudo::pam::Converser;
+use sudo::sudoers::TimestampType;
 use sudo::system::{time::Duration, timestamp::SessionRecordFile, Process};
 
-mod diagnostic;
-use diagnostic::diagnostic;
 mod pam;
 mod pipeline;
 
@@ -32,6 +35,13 @@ do this then this software is not suited for you at this time."
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
+fn load_conf() -> SudoConf {
+    SudoConf::load().unwrap_or_else(|error| {
+        diagnostic!("ignoring {SUDO_CONF_PATH}: {error}");
+        Default::default()
+    })
+}
+
 #[derive(Default)]
 pub struct SudoersPolicy {}
 
@@ -40,14 +50,20 @@ impl PolicyPlugin for SudoersPolicy {
     type Policy = sudo::sudoers::Judgement;
 
     fn init(&mut self) -> Result<Self::PreJudgementPolicy, Error> {
-        // TODO: move to global configuration
-        let sudoers_path = "/etc/sudoers.test";
+        let conf = load_conf();
+        let sudoers_path = &conf.sudoers_path();
 
         let (sudoers, syntax_errors) = sudo::sudoers::Sudoers::new(sudoers_path)
             .map_err(|e| Error::Configuration(format!("{e}")))?;
 
-        for sudo::sudoers::Error(pos, error) in syntax_errors {
-            diagnostic!("{error}", sudoers_path @ pos);
+        for sudo::sudoers::Error {
+            source,
+            location,
+            message,
+        } in syntax_errors
+        {
+            let path = source.as_deref().unwrap_or(sudoers_path);
+            diagnostic!("{message}", path @ location);
         }
 
         Ok(sudoers)
@@ -93,17 +109,27 @@ fn sudo_process() -> Result<(), Error> {
                 return Ok(());
             }
             SudoAction::ResetTimestamp => {
-                if let Some(scope) = determine_record_scope(&Process::new()) {
+                // the policy is not consulted, so records of any type are invalidated
+                let process = Process::new();
+                let scopes = [
+                    TimestampType::Tty,
+                    TimestampType::Ppid,
+                    TimestampType::Global,
+                ]
+                .into_iter()
+                .filter_map(|timestamp_type| determine_record_scope(&process, timestamp_type))
+                .collect::<Vec<_>>();
+                if !scopes.is_empty() {
                     let user = resolve_current_user()?;
                     let mut record_file =
                         SessionRecordFile::open_for_user(&user.name, Duration::seconds(0))?;
-                    record_file.disable(scope, None)?;
+                    for scope in scopes {
+                        record_file.disable(scope, None)?;
+                    }
                 }
                 return Ok(());
             }
-            SudoAction::Validate => {
-                unimplemented!();
-            }
+            SudoAction::Validate => options,
             SudoAction::Run(ref cmd) => {
                 if cmd.is_empty() && !options.shell && !options.login {
                     eprintln!("{}", help::USAGE_MSG);
@@ -112,11 +138,14 @@ fn sudo_process() -> Result<(), Error> {
                     options
                 }
             }
-            SudoAction::List(_) => {
-                unimplemented!();
-            }
-            SudoAction::Edit(_) => {
-                unimplemented!();
+            SudoAction::List(_) => options,
+            SudoAction::Edit(ref files) => {
+                if files.is_empty() {
+                    eprintln!("{}", help::USAGE_MSG);
+                    std::process::exit(1);
+                } else {
+                    options
+                }
             }
         },
         Err(e) => {
@@ -127,11 +156,49 @@ fn sudo_process() -> Result<(), Error> {
 
     unstable_warning();
 
+    // with -n, the user is never asked for a password, not even by a helper program
+    if sudo_options.non_interactive {
+        run_pipeline(PamAuthenticator::new_non_interactive(), sudo_options)
+    } else if sudo_options.askpass {
+        run_pipeline(
+            PamAuthenticator::new_askpass(askpass_program()?),
+            sudo_options,
+        )
+    } else {
+        run_pipeline(PamAuthenticator::new_cli(), sudo_options)
+    }
+}
+
+fn run_pipeline<C: Converser>(
+    authenticator: PamAuthenticator<C>,
+    sudo_options: SudoOptions,
+) -> Result<(), Error> {
     let mut pipeline = Pipeline {
         policy: SudoersPolicy::default(),
-        authenticator: PamAuthenticator::new_cli(),
+        authenticator,
     };
-    pipeline.run(sudo_options)
+    match sudo_options.action {
+        SudoAction::Validate => pipeline.run_validate(sudo_options),
+        SudoAction::List(_) => pipeline.run_list(sudo_options),
+        SudoAction::Edit(_) => pipeline.run_edit(sudo_options),
+        _ => pipeline.run(sudo_options),
+    }
+}
+
+/// The helper program that asks for a password for `sudo -A`: the one set in `SUDO_ASKPASS`,
+/// or otherwise the one configured in `sudo.conf`
+fn askpass_program() -> Result<PathBuf, Error> {
+    match env::var_os("SUDO_ASKPASS") {
+        Some(program) => Ok(program.into()),
+        None => load_conf()
+            .askpass_path()
+            .map(PathBuf::from)
+            .ok_or_else(|| {
+                Error::Configuration(
+                    "no askpass program specified, try setting SUDO_ASKPASS".to_string(),
+                )
+            }),
+    }
 }
 
 fn main() {
@@ -139,7 +206,7 @@ fn main() {
         Ok(()) => (),
         Err(error) => {
             diagnostic!("{error}");
-            std::process::exit(1);
+            std::process::exit(error.exit_code());
         }
     }
 }
@@ -1,12 +1,18 @@
 use std::fs::File;
+use std::path::PathBuf;
 
 use sudo::common::{error::Error, Context};
 use sudo::log::{auth_warn, user_warn};
-use sudo::pam::{CLIConverser, Converser, PamContext, PamError, PamErrorType, PamResult};
+use sudo::pam::{
+    AskpassConverser, CLIConverser, Converser, NonInteractiveConverser, PamContext, PamError,
+    PamErrorType, PamResult,
+};
+use sudo::sudoers::{Authentication, Lecture, TimestampType};
 use sudo::system::{
+    lecture,
     time::Duration,
     timestamp::{RecordScope, SessionRecordFile, TouchResult},
-    Process, WithProcess,
+    Process, User, WithProcess,
 };
 
 use crate::pipeline::AuthPlugin;
@@ -14,8 +20,16 @@ use crate::pipeline::AuthPlugin;
 /// Tries to determine a record match scope for the current context.
 /// This should never produce an error since any actual error should just be
 /// ignored and no session record file should be used in that case.
-pub fn determine_record_scope(process: &Process) -> Option<RecordScope> {
-    let tty = Process::tty_device_id(WithProcess::Current);
+pub fn determine_record_scope(
+    process: &Process,
+    timestamp_type: TimestampType,
+) -> Option<RecordScope> {
+    let tty = match timestamp_type {
+        TimestampType::Global => return Some(RecordScope::Global),
+        TimestampType::Tty => Process::tty_device_id(WithProcess::Current),
+        // the terminal is ignored, even if there is one
+        TimestampType::Ppid => Ok(None),
+    };
     if let Ok(Some(tty_device)) = tty {
         if let Ok(init_time) = Process::starting_time(WithProcess::Other(process.session_id)) {
             Some(RecordScope::TTY {
@@ -44,14 +58,16 @@ pub fn determine_record_scope(process: &Process) -> Option<RecordScope> {
 
 /// This should determine what the authentication status for the given record
 /// match limit and origin/target user from the context is.
-fn determine_auth_status(
+fn determine_auth_status<'a>(
     record_for: Option<RecordScope>,
-    context: &Context,
-) -> (bool, Option<SessionRecordFile<File>>) {
+    context: &'a Context,
+    auth_user: &User,
+    timeout: Duration,
+) -> (bool, Option<SessionRecordFile<'a, File>>) {
     if let (true, Some(record_for)) = (context.use_session_records, record_for) {
-        match SessionRecordFile::open_for_user(&context.current_user.name, Duration::minutes(15)) {
+        match SessionRecordFile::open_for_user(&context.current_user.name, timeout) {
             Ok(mut sr) => {
-                match sr.touch(record_for, context.current_user.uid) {
+                match sr.touch(record_for, auth_user.uid) {
                     // if a record was found and updated within the timeout, we do not need to authenticate
                     Ok(TouchResult::Updated { .. }) => (false, Some(sr)),
                     Ok(TouchResult::NotFound | TouchResult::Outdated { .. }) => (true, Some(sr)),
@@ -72,7 +88,20 @@ fn determine_auth_status(
     }
 }
 
-type PamBuilder<C> = dyn Fn(&Context) -> PamResult<PamContext<C>>;
+/// Create a new session record for the authenticating user, or refresh the existing one
+fn create_session_record(
+    mut session_records: SessionRecordFile<File>,
+    scope: RecordScope,
+    auth_user: &User,
+) {
+    // records are kept per user that authenticated, so a session with e.g. the
+    // password of root does not count when the user's own password is required
+    if let Err(e) = session_records.create(scope, auth_user.uid) {
+        auth_warn!("Could not update session record file with new record: {e}");
+    }
+}
+
+type PamBuilder<C> = dyn Fn(&Context, &Authentication, &User) -> PamResult<PamContext<C>>;
 
 pub struct PamAuthenticator<C: Converser> {
     builder: Box<PamBuilder<C>>,
@@ -81,7 +110,7 @@ pub struct PamAuthenticator<C: Converser> {
 
 impl<C: Converser> PamAuthenticator<C> {
     fn new(
-        initializer: impl Fn(&Context) -> PamResult<PamContext<C>> + 'static,
+        initializer: impl Fn(&Context, &Authentication, &User) -> PamResult<PamContext<C>> + 'static,
     ) -> PamAuthenticator<C> {
         PamAuthenticator {
             builder: Box::new(initializer),
@@ -90,11 +119,73 @@ impl<C: Converser> PamAuthenticator<C> {
     }
 }
 
+/// Determine the custom password prompt, if any, and whether it should replace all password
+/// prompts of PAM; a prompt given on the command line always does
+fn password_prompt(
+    context: &Context,
+    auth: &Authentication,
+    auth_user: &User,
+) -> (Option<String>, bool) {
+    let (prompt, prompt_override) = match &context.prompt {
+        Some(prompt) => (Some(prompt), true),
+        None => (auth.passprompt.as_ref(), auth.passprompt_override),
+    };
+    let prompt = prompt.map(|prompt| context.expand_prompt(prompt, &auth_user.name));
+
+    (prompt, prompt_override)
+}
+
 impl PamAuthenticator<CLIConverser> {
     pub fn new_cli() -> PamAuthenticator<CLIConverser> {
-        PamAuthenticator::new(|context| {
-            let mut pam = PamContext::builder_cli(context.stdin)
-                .target_user(&context.current_user.name)
+        PamAuthenticator::new(|context, auth, auth_user| {
+            let (prompt, prompt_override) = password_prompt(context, auth, auth_user);
+
+            let mut pam = PamContext::builder_cli(
+                context.stdin,
+                auth.passwd_timeout,
+                prompt,
+                prompt_override,
+            )
+            .target_user(&auth_user.name)
+            .service_name("sudo")
+            .build()?;
+            pam.mark_silent(true);
+            pam.mark_allow_null_auth_token(false);
+            Ok(pam)
+        })
+    }
+}
+
+impl PamAuthenticator<AskpassConverser> {
+    /// Authenticate by asking for the password with the helper `program`, which is run as the
+    /// invoking user
+    pub fn new_askpass(program: PathBuf) -> PamAuthenticator<AskpassConverser> {
+        PamAuthenticator::new(move |context, auth, auth_user| {
+            let (prompt, prompt_override) = password_prompt(context, auth, auth_user);
+
+            let mut pam = PamContext::builder_askpass(
+                program.clone(),
+                context.current_user.clone(),
+                auth.passwd_timeout,
+                prompt,
+                prompt_override,
+            )
+            .target_user(&auth_user.name)
+            .service_name("sudo")
+            .build()?;
+            pam.mark_silent(true);
+            pam.mark_allow_null_auth_token(false);
+            Ok(pam)
+        })
+    }
+}
+
+impl PamAuthenticator<NonInteractiveConverser> {
+    /// Authenticate without asking the user for anything; this fails if a password is needed
+    pub fn new_non_interactive() -> PamAuthenticator<NonInteractiveConverser> {
+        PamAuthenticator::new(|_context, _auth, auth_user| {
+            let mut pam = PamContext::builder_non_interactive()
+                .target_user(&auth_user.name)
                 .service_name("sudo")
                 .build()?;
             pam.mark_silent(true);
@@ -105,26 +196,42 @@ impl PamAuthenticator<CLIConverser> {
 }
 
 impl<C: Converser> AuthPlugin for PamAuthenticator<C> {
-    fn init(&mut self, context: &Context) -> Result<(), Error> {
-        self.pam = Some((self.builder)(context)?);
+    fn init(
+        &mut self,
+        context: &Context,
+        auth: &Authentication,
+        auth_user: &User,
+    ) -> Result<(), Error> {
+        self.pam = Some((self.builder)(context, auth, auth_user)?);
         Ok(())
     }
 
-    fn authenticate(&mut self, context: &Context) -> Result<(), Error> {
-        let pam = self
-            .pam
+    fn authenticate(
+        &mut self,
+        context: &Context,
+        auth: &Authentication,
+        auth_user: &User,
+    ) -> Result<(), Error> {
+        self.pam
             .as_mut()
-            .expect("Pam must be initialized before authenticate");
-        pam.set_user(&context.current_user.name)?;
+            .expect("Pam must be initialized before authenticate")
+            .set_user(&auth_user.name)?;
 
         // determine session limit
-        let scope = determine_record_scope(&context.process);
+        let scope = determine_record_scope(&context.process, auth.timestamp_type);
 
         // only if there is an interactive terminal or parent process we can store session information
-        let (must_authenticate, records_file) = determine_auth_status(scope, context);
+        let (must_authenticate, records_file) =
+            determine_auth_status(scope, context, auth_user, auth.timestamp_timeout);
 
         if must_authenticate {
-            let mut max_tries = 3;
+            // there is no point in lecturing a user that will not be asked for a password
+            let lectured = !context.non_interactive && self.lecture(context, auth);
+
+            let pam = self
+                .pam
+                .as_mut()
+                .expect("Pam must be initialized before authenticate");
             let mut current_try = 0;
             loop {
                 current_try += 1;
@@ -139,40 +246,99 @@ impl<C: Converser> AuthPlugin for PamAuthenticator<C> {
 
                     // there was an authentication error, we can retry
                     Err(PamError::Pam(PamErrorType::AuthError, _)) => {
-                        max_tries -= 1;
-                        if max_tries == 0 {
+                        if current_try >= auth.passwd_tries {
                             return Err(Error::MaxAuthAttempts(current_try));
                         } else {
                             user_warn!("Authentication failed, try again.");
                         }
                     }
 
+                    // the user could not be asked for a password
+                    Err(PamError::InteractionRequired) => {
+                        return Err(Error::InteractionRequired);
+                    }
+
                     // there was another pam error, return the error
                     Err(e) => {
                         return Err(e.into());
                     }
                 }
             }
-            if let (Some(mut session_records), Some(scope)) = (records_file, scope) {
-                match session_records.create(scope, context.current_user.uid) {
-                    Ok(_) => (),
-                    Err(e) => {
-                        auth_warn!("Could not update session record file with new record: {e}");
-                    }
+
+            // the lecture only counts as received once the user has authenticated
+            if lectured {
+                if let Err(e) = lecture::set_lectured(&context.current_user.name) {
+                    auth_warn!("Could not record that the user was lectured: {e}");
                 }
             }
+
+            if let (Some(session_records), Some(scope)) = (records_file, scope) {
+                create_session_record(session_records, scope, auth_user);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn refresh_session(&mut self, context: &Context, auth: &Authentication, auth_user: &User) {
+        if !context.use_session_records {
+            return;
+        }
+
+        let Some(scope) = determine_record_scope(&context.process, auth.timestamp_type) else {
+            return;
+        };
+
+        match SessionRecordFile::open_for_user(&context.current_user.name, auth.timestamp_timeout) {
+            Ok(session_records) => create_session_record(session_records, scope, auth_user),
+            Err(e) => auth_warn!("Could not use session information: {e}"),
+        }
+    }
+
+    fn lecture(&mut self, context: &Context, auth: &Authentication) -> bool {
+        let needed = match auth.lecture {
+            Lecture::Never => false,
+            Lecture::Always => true,
+            Lecture::Once => !lecture::already_lectured(&context.current_user.name),
+        };
+        if !needed {
+            return false;
+        }
+
+        let pam = self
+            .pam
+            .as_ref()
+            .expect("Pam must be initialized before lecture");
+
+        // not being able to show the lecture is not a reason to fail; without a terminal, asking
+        // for the password will fail right after anyway
+        match pam.show_text(&lecture::lecture_text(auth.lecture_file.as_deref())) {
+            Ok(()) => true,
+            Err(e) => {
+                auth_warn!("Could not show the lecture: {e}");
+                false
+            }
         }
+    }
 
+    fn validate_account(&mut self) -> Result<(), Error> {
+        let pam = self
+            .pam
+            .as_mut()
+            .expect("Pam must be initialized before validate_account");
+
+        pam.validate_account()?;
         Ok(())
     }
 
     fn pre_exec(&mut self, _context: &Context) -> Result<(), Error> {
+        self.validate_account()?;
+
         let pam = self
             .pam
             .as_mut()
             .expect("Pam must be initialized before pre_exec");
 
-        pam.validate_account()?;
         pam.open_session()?;
         Ok(())
     }
@@ -4,7 +4,11 @@ use sudo::cli::SudoOptions;
 use sudo::common::{Context, Error};
 use sudo::env::environment;
 use sudo::exec::ExitReason;
-use sudo::sudoers::{Authorization, DirChange, Policy, PreJudgementPolicy};
+use sudo::sudoers::{
+    AuthenticatingUser, Authentication, Authorization, DirChange, Policy, PreJudgementPolicy,
+    Request, Sudoers,
+};
+use sudo::system::User;
 
 pub trait PolicyPlugin {
     type PreJudgementPolicy: PreJudgementPolicy;
@@ -19,8 +23,25 @@ pub trait PolicyPlugin {
 }
 
 pub trait AuthPlugin {
-    fn init(&mut self, context: &Context) -> Result<(), Error>;
-    fn authenticate(&mut self, context: &Context) -> Result<(), Error>;
+    fn init(
+        &mut self,
+        context: &Context,
+        auth: &Authentication,
+        auth_user: &User,
+    ) -> Result<(), Error>;
+    fn authenticate(
+        &mut self,
+        context: &Context,
+        auth: &Authentication,
+        auth_user: &User,
+    ) -> Result<(), Error>;
+    /// Show the lecture if it is due; returns whether it was shown, in which case it should be
+    /// recorded once the user has authenticated
+    fn lecture(&mut self, context: &Context, auth: &Authentication) -> bool;
+    /// Create or refresh the session record without authenticating, for when the policy does not
+    /// require a password
+    fn refresh_session(&mut self, context: &Context, auth: &Authentication, auth_user: &User);
+    fn validate_account(&mut self) -> Result<(), Error>;
     fn pre_exec(&mut self, context: &Context) -> Result<(), Error>;
     fn cleanup(&mut self);
 }
@@ -33,10 +54,7 @@ pub struct Pipeline<Policy: PolicyPlugin, Auth: AuthPlugin> {
 impl<Policy: PolicyPlugin, Auth: AuthPlugin> Pipeline<Policy, Auth> {
     pub fn run(&mut self, sudo_options: SudoOptions) -> Result<(), Error> {
         let pre = self.policy.init()?;
-        let secure_path: String = pre
-            .secure_path()
-            .unwrap_or_else(|| std::env::var("PATH").unwrap_or_default());
-        let mut context = Context::build_from_options(sudo_options, secure_path)?;
+        let mut context = build_context(sudo_options, &pre)?;
 
         let policy = self.policy.judge(pre, &context)?;
         let authorization = policy.authorization();
@@ -50,9 +68,12 @@ impl<Policy: PolicyPlugin, Auth: AuthPlugin> Pipeline<Policy, Auth> {
             }
             Authorization::Allowed { must_authenticate } => {
                 self.apply_policy_to_context(&mut context, &policy)?;
-                self.authenticator.init(&context)?;
+                let auth = policy.authentication();
+                let auth_user = auth_user(&context, &auth)?;
+                self.authenticator.init(&context, &auth, &auth_user)?;
                 if must_authenticate {
-                    self.authenticator.authenticate(&context)?;
+                    self.authenticator
+                        .authenticate(&context, &auth, &auth_user)?;
                 }
             }
         }
@@ -61,7 +82,7 @@ impl<Policy: PolicyPlugin, Auth: AuthPlugin> Pipeline<Policy, Auth> {
 
         // build environment
         let current_env = std::env::vars_os().collect();
-        let target_env = environment::get_target_environment(current_env, &context, &policy);
+        let target_env = environment::get_target_environment(current_env, &context, &policy)?;
 
         let pid = context.process.pid;
 
@@ -83,6 +104,36 @@ impl<Policy: PolicyPlugin, Auth: AuthPlugin> Pipeline<Policy, Auth> {
         Ok(())
     }
 
+    pub fn run_edit(&mut self, sudo_options: SudoOptions) -> Result<(), Error> {
+        let pre = self.policy.init()?;
+        let context = build_context(sudo_options, &pre)?;
+
+        let policy = self.policy.judge(pre, &context)?;
+        match policy.authorization() {
+            Authorization::Forbidden => {
+                return Err(Error::auth(&format!(
+                    "I'm sorry {}. I'm afraid I can't do that",
+                    context.current_user.name
+                )));
+            }
+            Authorization::Allowed { must_authenticate } => {
+                let auth = policy.authentication();
+                let auth_user = auth_user(&context, &auth)?;
+                self.authenticator.init(&context, &auth, &auth_user)?;
+                if must_authenticate {
+                    self.authenticator
+                        .authenticate(&context, &auth, &auth_user)?;
+                }
+            }
+        }
+
+        self.authenticator.pre_exec(&context)?;
+        let result = sudo::edit::edit_files(&context, &policy.editor(), policy.env_editor());
+        self.authenticator.cleanup();
+
+        result
+    }
+
     fn apply_policy_to_context(
         &mut self,
         context: &mut Context,
@@ -100,6 +151,142 @@ impl<Policy: PolicyPlugin, Auth: AuthPlugin> Pipeline<Policy, Auth> {
             }
         }
 
+        // a command whose digest was checked is executed through the file that was checked
+        if let Some(file) = policy.command_file() {
+            context.command_file = Some(file.try_clone()?);
+        }
+
+        Ok(())
+    }
+}
+
+impl<Policy, Auth: AuthPlugin> Pipeline<Policy, Auth>
+where
+    Policy: PolicyPlugin<PreJudgementPolicy = Sudoers>,
+{
+    pub fn run_validate(&mut self, sudo_options: SudoOptions) -> Result<(), Error> {
+        let pre = self.policy.init()?;
+        let context = build_context(sudo_options, &pre)?;
+
+        match pre.check_validate_permission(&context.current_user, &context.hostname) {
+            Authorization::Forbidden => {
+                return Err(Error::auth(&format!(
+                    "I'm sorry {}. I'm afraid I can't do that",
+                    context.current_user.name
+                )));
+            }
+            Authorization::Allowed { must_authenticate } => {
+                let auth = pre.authentication(&context.current_user, &context.hostname);
+                let auth_user = auth_user(&context, &auth)?;
+                self.authenticator.init(&context, &auth, &auth_user)?;
+                // authenticating creates or refreshes the session record, without a password
+                // the record is still updated so that later invocations can rely on it
+                if must_authenticate {
+                    self.authenticator
+                        .authenticate(&context, &auth, &auth_user)?;
+                } else {
+                    self.authenticator
+                        .refresh_session(&context, &auth, &auth_user);
+                }
+                self.authenticator.validate_account()?;
+            }
+        }
+
         Ok(())
     }
+
+    pub fn run_list(&mut self, sudo_options: SudoOptions) -> Result<(), Error> {
+        let long_format = sudo_options.long_list;
+        let pre = self.policy.init()?;
+        let context = build_context(sudo_options, &pre)?;
+        let list_user = context.other_user.as_ref().unwrap_or(&context.current_user);
+
+        match pre.check_list_permission(&context.current_user, &context.hostname, list_user) {
+            Authorization::Forbidden => {
+                return Err(Error::auth(&format!(
+                    "I'm sorry {}. I'm afraid I can't do that",
+                    context.current_user.name
+                )));
+            }
+            Authorization::Allowed { must_authenticate } => {
+                let auth = pre.authentication(&context.current_user, &context.hostname);
+                let auth_user = auth_user(&context, &auth)?;
+                self.authenticator.init(&context, &auth, &auth_user)?;
+                if must_authenticate {
+                    self.authenticator
+                        .authenticate(&context, &auth, &auth_user)?;
+                }
+                self.authenticator.validate_account()?;
+            }
+        }
+
+        if context.command.command.as_os_str().is_empty() {
+            let entries = pre.matching_entries(list_user, &context.hostname);
+            if entries.is_empty() {
+                println!(
+                    "User {} is not allowed to run sudo on {}.",
+                    list_user.name, context.hostname
+                );
+                return Ok(());
+            }
+
+            println!(
+                "User {} may run the following commands on {}:",
+                list_user.name, context.hostname
+            );
+            for entry in entries {
+                if long_format {
+                    println!("\n{entry:#}");
+                } else {
+                    println!("    {entry}");
+                }
+            }
+        } else {
+            // like the original sudo, only show the fully qualified command if it is allowed
+            let judgement = pre.check(
+                list_user,
+                &context.hostname,
+                Request {
+                    user: &context.target_user,
+                    group: &context.target_group,
+                    command: &context.command.command,
+                    arguments: &context.command.arguments,
+                },
+            );
+            if let Authorization::Forbidden = judgement.authorization() {
+                exit(1);
+            }
+
+            let command = &context.command;
+            let mut line = command.command.display().to_string();
+            for arg in &command.arguments {
+                line.push(' ');
+                line.push_str(arg);
+            }
+            println!("{line}");
+        }
+
+        Ok(())
+    }
+}
+
+/// Determine the user whose password has to be entered, as the policy prescribes
+fn auth_user(context: &Context, auth: &Authentication) -> Result<User, Error> {
+    match auth.credential {
+        AuthenticatingUser::InvokingUser => Ok(context.current_user.clone()),
+        AuthenticatingUser::TargetUser => Ok(context.target_user.clone()),
+        AuthenticatingUser::Root => {
+            User::from_uid(0)?.ok_or_else(|| Error::UserNotFound("root".to_string()))
+        }
+    }
+}
+
+fn build_context(
+    sudo_options: SudoOptions,
+    pre: &impl PreJudgementPolicy,
+) -> Result<Context, Error> {
+    Context::build_from_options(sudo_options, |current_user, hostname| {
+        pre.secure_path(current_user, hostname)
+            .unwrap_or_else(|| std::env::var("PATH").unwrap_or_default())
+    })
 }
@@ -10,11 +10,24 @@ fn short_preserve_env_with_var_fails() {
     assert!(cmd.is_err())
 }
 
+/// Passing '-E' or '--preserve-env' without an argument sets 'short_preserve_env'
+#[test]
+fn short_preserve_env() {
+    let cmd = SudoOptions::try_parse_from(["sudo", "-E", "ls"]).unwrap();
+    assert!(cmd.short_preserve_env);
+    assert!(cmd.preserve_env.is_empty());
+
+    let cmd = SudoOptions::try_parse_from(["sudo", "--preserve-env", "ls"]).unwrap();
+    assert!(cmd.short_preserve_env);
+    assert_eq!(cmd.action, SudoAction::Run(vec!["ls".to_string()]));
+}
+
 /// Passing '--preserve-env' with an argument fills 'preserve_env', 'short_preserve_env' stays 'false'
 #[test]
 fn preserve_env_with_var() {
     let cmd = SudoOptions::try_parse_from(["sudo", "--preserve-env=some_argument"]).unwrap();
     assert_eq!(cmd.preserve_env, vec!["some_argument"]);
+    assert!(!cmd.short_preserve_env);
 }
 
 /// Passing '--preserve-env' with several arguments fills 'preserve_env', 'short_preserve_env' stays 'false'
@@ -186,6 +199,15 @@ fn set_home() {
     assert!(cmd.set_home);
 }
 
+#[test]
+fn askpass() {
+    let cmd = SudoOptions::try_parse_from(["sudo", "-A", "true"]).unwrap();
+    assert!(cmd.askpass);
+
+    let cmd = SudoOptions::try_parse_from(["sudo", "--askpass", "true"]).unwrap();
+    assert!(cmd.askpass);
+}
+
 #[test]
 fn non_interactive() {
     let cmd = SudoOptions::try_parse_from(["sudo", "-n"]).unwrap();
@@ -270,6 +292,18 @@ fn chroot() {
     assert_eq!(cmd.chroot, Some(PathBuf::from("/some/path")));
 }
 
+#[test]
+fn prompt() {
+    let cmd = SudoOptions::try_parse_from(["sudo", "-p", "pw for %p: ", "true"]).unwrap();
+    assert_eq!(cmd.prompt.as_deref(), Some("pw for %p: "));
+
+    let cmd = SudoOptions::try_parse_from(["sudo", "--prompt", "secret?", "true"]).unwrap();
+    assert_eq!(cmd.prompt.as_deref(), Some("secret?"));
+
+    let cmd = SudoOptions::try_parse_from(["sudo", "--prompt=", "true"]).unwrap();
+    assert_eq!(cmd.prompt.as_deref(), Some(""));
+}
+
 #[test]
 fn other_user() {
     let cmd = SudoOptions::try_parse_from(["sudo", "-Uferris"]).unwrap();
@@ -352,6 +386,14 @@ fn list() {
 
     let cmd = SudoOptions::try_parse_from(["sudo", "--list"]).unwrap();
     assert_eq!(cmd.action, SudoAction::List(vec![]));
+    assert!(!cmd.long_list);
+
+    let cmd = SudoOptions::try_parse_from(["sudo", "-ll"]).unwrap();
+    assert_eq!(cmd.action, SudoAction::List(vec![]));
+    assert!(cmd.long_list);
+
+    let cmd = SudoOptions::try_parse_from(["sudo", "-l", "--list"]).unwrap();
+    assert!(cmd.long_list);
 }
 
 #[test]
@@ -1,7 +1,9 @@
 use std::collections::HashSet;
+use std::ffi::OsStr;
 use sudo::cli::SudoOptions;
-use sudo::common::{CommandAndArguments, Context, Environment};
+use sudo::common::{context::LaunchType, CommandAndArguments, Context, Environment};
 use sudo::env::environment::get_target_environment;
+use sudo::sudoers::{Judgement, Policy};
 use sudo::system::{Group, Process, User};
 
 const TESTS: &str = "
@@ -117,6 +119,7 @@ fn create_test_context(sudo_options: &SudoOptions) -> Context {
     Context {
         hostname: "test-ubuntu".to_string(),
         command,
+        command_file: None,
         current_user: current_user.clone(),
         target_user: if sudo_options.user.as_deref() == Some("test") {
             current_user
@@ -129,13 +132,24 @@ fn create_test_context(sudo_options: &SudoOptions) -> Context {
             root_group
         },
         set_home: sudo_options.set_home,
+        prompt: sudo_options.prompt.clone(),
+        non_interactive: sudo_options.non_interactive,
         preserve_env: sudo_options.preserve_env.clone(),
+        short_preserve_env: sudo_options.short_preserve_env,
+        env_var_list: sudo_options.env_var_list.clone(),
         path,
-        launch: sudo::common::context::LaunchType::Direct,
+        launch: if sudo_options.login {
+            LaunchType::Login
+        } else if sudo_options.shell {
+            LaunchType::Shell
+        } else {
+            LaunchType::Direct
+        },
         chdir: sudo_options.directory.clone(),
         stdin: sudo_options.stdin,
         process: Process::new(),
         use_session_records: false,
+        other_user: None,
     }
 }
 
@@ -156,7 +170,8 @@ fn test_environment_variable_filtering() {
         let options = SudoOptions::try_parse_from(cmd.split_whitespace()).unwrap();
         let settings = sudo::sudoers::Judgement::default();
         let context = create_test_context(&options);
-        let resulting_env = get_target_environment(initial_env.clone(), &context, &settings);
+        let resulting_env =
+            get_target_environment(initial_env.clone(), &context, &settings).unwrap();
 
         let resulting_env = environment_to_set(resulting_env);
         let expected_env = environment_to_set(expected_env);
@@ -172,3 +187,188 @@ fn test_environment_variable_filtering() {
         );
     }
 }
+
+#[test]
+fn test_preserving_environment_variables() {
+    let initial_env = parse_env_commands(TESTS).remove(0).1;
+    let settings = sudo::sudoers::Judgement::default();
+    let target_env = |cmd: &str| {
+        let options = SudoOptions::try_parse_from(cmd.split_whitespace()).unwrap();
+        let context = create_test_context(&options);
+        get_target_environment(initial_env.clone(), &context, &settings)
+    };
+
+    // preserving the entire environment requires permission to set any variable
+    assert!(target_env("sudo -E env").is_err());
+
+    let env = target_env("sudo --preserve-env=LANG,TERM,UNSET env").unwrap();
+    assert_eq!(env[OsStr::new("LANG")], "en_US.UTF-8");
+    assert!(!env.contains_key(OsStr::new("UNSET")));
+
+    assert!(target_env("sudo --preserve-env=FOO env").is_err());
+    assert!(target_env("sudo FOO=BAR env").is_err());
+}
+
+/// The default policy, with some of the environment settings changed
+struct TestPolicy {
+    default: Judgement,
+    env_keep: HashSet<String>,
+    env_reset: bool,
+    always_set_home: bool,
+    set_home: bool,
+    set_logname: bool,
+}
+
+impl Default for TestPolicy {
+    fn default() -> Self {
+        let default = Judgement::default();
+        TestPolicy {
+            env_keep: default.env_keep().clone(),
+            env_reset: default.env_reset(),
+            always_set_home: default.always_set_home(),
+            set_home: default.set_home(),
+            set_logname: default.set_logname(),
+            default,
+        }
+    }
+}
+
+impl Policy for TestPolicy {
+    fn env_keep(&self) -> &HashSet<String> {
+        &self.env_keep
+    }
+
+    fn env_check(&self) -> &HashSet<String> {
+        self.default.env_check()
+    }
+
+    fn env_delete(&self) -> &HashSet<String> {
+        self.default.env_delete()
+    }
+
+    fn env_reset(&self) -> bool {
+        self.env_reset
+    }
+
+    fn always_set_home(&self) -> bool {
+        self.always_set_home
+    }
+
+    fn set_home(&self) -> bool {
+        self.set_home
+    }
+
+    fn set_logname(&self) -> bool {
+        self.set_logname
+    }
+
+    fn secure_path(&self) -> Option<String> {
+        self.default.secure_path()
+    }
+
+    fn editor(&self) -> String {
+        self.default.editor()
+    }
+
+    fn env_editor(&self) -> bool {
+        self.default.env_editor()
+    }
+}
+
+#[test]
+fn test_environment_without_reset() {
+    let mut initial_env = parse_env_commands(TESTS).remove(0).1;
+    initial_env.insert("IFS".into(), " ".into());
+    initial_env.insert("BASH_FUNC_foo%%".into(), "() {  echo foo\n}".into());
+    initial_env.insert("TZ".into(), "../../etc/shadow".into());
+
+    let options = SudoOptions::try_parse_from(["sudo", "env"]).unwrap();
+    let context = create_test_context(&options);
+    let settings = TestPolicy {
+        env_reset: false,
+        ..Default::default()
+    };
+    let env = get_target_environment(initial_env, &context, &settings).unwrap();
+
+    // variables that are not in env_keep are inherited
+    assert_eq!(env[OsStr::new("FOO")], "BAR");
+    assert_eq!(env[OsStr::new("PWD")], "/home/test");
+    // but those in env_delete or failing env_check are not
+    assert!(!env.contains_key(OsStr::new("IFS")));
+    assert!(!env.contains_key(OsStr::new("BASH_FUNC_foo%%")));
+    assert!(!env.contains_key(OsStr::new("TZ")));
+    // and the target user is still set
+    assert_eq!(env[OsStr::new("USER")], "root");
+    assert_eq!(env[OsStr::new("LOGNAME")], "root");
+    assert_eq!(env[OsStr::new("SUDO_USER")], "test");
+}
+
+#[test]
+fn test_home_and_logname() {
+    let mut initial_env = parse_env_commands(TESTS).remove(0).1;
+    initial_env.insert("LOGNAME".into(), "test".into());
+    initial_env.insert("USER".into(), "test".into());
+    initial_env.insert("LOGIN".into(), "test".into());
+
+    let target_env = |cmd: &str, settings: &TestPolicy| {
+        let options = SudoOptions::try_parse_from(cmd.split_whitespace()).unwrap();
+        let context = create_test_context(&options);
+        let env = get_target_environment(initial_env.clone(), &context, settings).unwrap();
+        let get = |key: &str| {
+            env.get(OsStr::new(key))
+                .map(|value| value.to_str().unwrap().to_string())
+        };
+        (get("HOME"), get("LOGNAME"), get("USER"), get("LOGIN"))
+    };
+    let home = |cmd: &str, settings: &TestPolicy| target_env(cmd, settings).0.unwrap();
+
+    // with env_reset, HOME is only preserved if it is in env_keep
+    let mut settings = TestPolicy::default();
+    assert_eq!(home("sudo env", &settings), "/root");
+    settings.env_keep.insert("HOME".to_string());
+    assert_eq!(home("sudo env", &settings), "/home/test");
+    assert_eq!(home("sudo -H env", &settings), "/root");
+    assert_eq!(home("sudo -i env", &settings), "/root");
+    assert_eq!(home("sudo -s env", &settings), "/home/test");
+    settings.set_home = true;
+    assert_eq!(home("sudo -s env", &settings), "/root");
+    assert_eq!(home("sudo env", &settings), "/home/test");
+    settings.always_set_home = true;
+    assert_eq!(home("sudo env", &settings), "/root");
+
+    // without env_reset, HOME is preserved unless it is explicitly set
+    let mut settings = TestPolicy {
+        env_reset: false,
+        ..Default::default()
+    };
+    assert_eq!(home("sudo env", &settings), "/home/test");
+    assert_eq!(home("sudo -H env", &settings), "/root");
+    assert_eq!(home("sudo -i env", &settings), "/root");
+    settings.always_set_home = true;
+    assert_eq!(home("sudo env", &settings), "/root");
+
+    let root = || Some("root".to_string());
+    let test = || Some("test".to_string());
+
+    // without env_reset, set_logname determines whether LOGNAME, USER and LOGIN are changed
+    assert_eq!(
+        target_env("sudo env", &settings),
+        (Some("/root".to_string()), root(), root(), root())
+    );
+    settings.set_logname = false;
+    let (_, logname, user, login) = target_env("sudo env", &settings);
+    assert_eq!((logname, user, login), (test(), test(), test()));
+    // but a login shell always gets a fresh environment
+    let (_, logname, user, login) = target_env("sudo -i env", &settings);
+    assert_eq!((logname, user, login), (root(), root(), None));
+
+    // with env_reset, LOGNAME and USER are set unless they are in env_keep
+    let mut settings = TestPolicy::default();
+    let (_, logname, user, login) = target_env("sudo env", &settings);
+    assert_eq!((logname, user, login), (root(), root(), None));
+    settings.env_keep.insert("LOGNAME".to_string());
+    let (_, logname, user, _) = target_env("sudo env", &settings);
+    assert_eq!((logname, user), (test(), test()));
+    let (_, logname, user, _) = target_env("sudo -i env", &settings);
+    assert_eq!((logname, user), (root(), root()));
+}
@@ -6,7 +6,6 @@ use crate::{
 use sudo_test::{Command, Env, User};
 
 #[test]
-#[ignore = "gh399"]
 fn default_lecture_shown_once() -> Result<()> {
     let env = Env([SUDOERS_ROOT_ALL, SUDOERS_ONCE_LECTURE, SUDOERS_USER_ALL_ALL])
         .user(User(USERNAME).password(PASSWORD))
@@ -33,7 +32,6 @@ fn default_lecture_shown_once() -> Result<()> {
 }
 
 #[test]
-#[ignore = "gh399"]
 fn lecture_in_stderr() -> Result<()> {
     let env = Env([SUDOERS_ROOT_ALL, SUDOERS_ONCE_LECTURE, SUDOERS_USER_ALL_ALL])
         .user(User(USERNAME).password(PASSWORD))
@@ -0,0 +1,206 @@
+#[derive(Default, Debug, PartialEq)]
+pub struct VisudoOptions {
+    pub file: Option<String>,
+    pub check: bool,
+    pub quiet: bool,
+    pub strict: bool,
+    pub help: bool,
+    pub version: bool,
+}
+
+type OptionSetter = dyn Fn(&mut VisudoOptions, Option<String>) -> Result<(), String>;
+
+struct VisudoOption {
+    short: char,
+    long: &'static str,
+    takes_argument: bool,
+    set: &'static OptionSetter,
+}
+
+impl VisudoOptions {
+    const VISUDO_OPTIONS: &[VisudoOption] = &[
+        VisudoOption {
+            short: 'c',
+            long: "check",
+            takes_argument: false,
+            set: &|options, _| {
+                options.check = true;
+                Ok(())
+            },
+        },
+        VisudoOption {
+            short: 'f',
+            long: "file",
+            takes_argument: true,
+            set: &|options, argument| {
+                if argument.is_some() {
+                    options.file = argument;
+                } else {
+                    Err("no file provided")?
+                }
+
+                Ok(())
+            },
+        },
+        VisudoOption {
+            short: 'q',
+            long: "quiet",
+            takes_argument: false,
+            set: &|options, _| {
+                options.quiet = true;
+                Ok(())
+            },
+        },
+        VisudoOption {
+            short: 's',
+            long: "strict",
+            takes_argument: false,
+            set: &|options, _| {
+                options.strict = true;
+                Ok(())
+            },
+        },
+        VisudoOption {
+            short: 'h',
+            long: "help",
+            takes_argument: false,
+            set: &|options, _| {
+                options.help = true;
+                Ok(())
+            },
+        },
+        VisudoOption {
+            short: 'V',
+            long: "version",
+            takes_argument: false,
+            set: &|options, _| {
+                options.version = true;
+                Ok(())
+            },
+        },
+    ];
+
+    pub fn from_env() -> Result<VisudoOptions, String> {
+        // skip the name of the binary
+        let args = std::env::args().skip(1).collect();
+
+        Self::parse_arguments(args)
+    }
+
+    /// parse visudo arguments into VisudoOptions struct
+    fn parse_arguments(arguments: Vec<String>) -> Result<VisudoOptions, String> {
+        let mut options = VisudoOptions::default();
+        let mut arg_iter = arguments.into_iter();
+
+        while let Some(arg) = arg_iter.next() {
+            if let Some(long) = arg.strip_prefix("--") {
+                // parse assignments like '--file=/etc/sudoers'
+                let (name, value) = match long.split_once('=') {
+                    Some((name, value)) => (name, Some(value.to_string())),
+                    None => (long, None),
+                };
+                let Some(option) = Self::VISUDO_OPTIONS.iter().find(|o| o.long == name) else {
+                    Err(format!("unrecognized option '{arg}'"))?
+                };
+                if option.takes_argument {
+                    let value = value.or_else(|| arg_iter.next());
+                    (option.set)(&mut options, value)?;
+                } else if value.is_some() {
+                    Err(format!("'--{}' does not take any arguments", option.long))?;
+                } else {
+                    (option.set)(&mut options, None)?;
+                }
+            } else if let Some(flags) = arg.strip_prefix('-') {
+                // flags can be grouped, so we loop over the the characters
+                for (n, char) in flags.char_indices() {
+                    let Some(option) = Self::VISUDO_OPTIONS.iter().find(|o| o.short == char) else {
+                        Err(format!("invalid option -- '{char}'"))?
+                    };
+                    if option.takes_argument {
+                        // the argument is either the rest of this flag group or the next argument
+                        let rest = &flags[n + char.len_utf8()..];
+                        let value = if rest.is_empty() {
+                            arg_iter.next()
+                        } else {
+                            Some(rest.to_string())
+                        };
+                        (option.set)(&mut options, value)?;
+                        break;
+                    } else {
+                        (option.set)(&mut options, None)?;
+                    }
+                }
+            } else {
+                Err(format!("too many arguments: '{arg}'"))?;
+            }
+        }
+
+        Ok(options)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::VisudoOptions;
+
+    fn parse(args: &[&str]) -> Result<VisudoOptions, String> {
+        VisudoOptions::parse_arguments(args.iter().map(|s| s.to_string()).collect())
+    }
+
+    #[test]
+    fn it_parses_no_arguments() {
+        assert_eq!(parse(&[]).unwrap(), VisudoOptions::default());
+    }
+
+    #[test]
+    fn it_parses_help_and_version() {
+        let expected = VisudoOptions {
+            help: true,
+            version: true,
+            ..Default::default()
+        };
+        assert_eq!(expected, parse(&["-hV"]).unwrap());
+        assert_eq!(expected, parse(&["--help", "-V"]).unwrap());
+    }
+
+    #[test]
+    fn it_parses_file() {
+        let expected = VisudoOptions {
+            file: Some("/etc/sudoers.d/ferris".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(expected, parse(&["-f", "/etc/sudoers.d/ferris"]).unwrap());
+        assert_eq!(expected, parse(&["-f/etc/sudoers.d/ferris"]).unwrap());
+        assert_eq!(
+            expected,
+            parse(&["--file", "/etc/sudoers.d/ferris"]).unwrap()
+        );
+        assert_eq!(expected, parse(&["--file=/etc/sudoers.d/ferris"]).unwrap());
+        assert!(parse(&["-f"]).is_err());
+    }
+
+    #[test]
+    fn it_parses_check_options() {
+        let expected = VisudoOptions {
+            file: Some("sudoers".to_string()),
+            check: true,
+            quiet: true,
+            strict: true,
+            ..Default::default()
+        };
+        assert_eq!(expected, parse(&["-cqsf", "sudoers"]).unwrap());
+        assert_eq!(expected, parse(&["-c", "-q", "-s", "-fsudoers"]).unwrap());
+        assert_eq!(
+            expected,
+            parse(&["--check", "--quiet", "--strict", "--file=sudoers"]).unwrap()
+        );
+    }
+
+    #[test]
+    fn it_rejects_unknown_options() {
+        assert!(parse(&["-x"]).is_err());
+        assert!(parse(&["--extra"]).is_err());
+        assert!(parse(&["--check=yes"]).is_err());
+        assert!(parse(&["sudoers"]).is_err());
+    }
+}
@@ -0,0 +1,331 @@
+#![forbid(unsafe_code)]
+
+//! `visudo` edits the sudoers file safely: the file is locked, the changes are made to a
+//! temporary copy which is checked for errors, and only then is it installed.
+
+use std::fs::{self, File, OpenOptions, Permissions};
+use std::io::{self, ErrorKind, Write};
+use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::process::{exit, Command};
+
+use cli::VisudoOptions;
+use sudo::config::{SudoConf, SUDO_CONF_PATH};
+use sudo::diagnostic::cited_error;
+use sudo::sudoers::{PreJudgementPolicy, Sudoers};
+use sudo::system::{
+    fchown,
+    file::{file_error, Lockable},
+    hostname, User,
+};
+
+mod cli;
+
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+const USAGE_MSG: &str = "usage: visudo [-chqsV] [-f sudoers]";
+
+const HELP_MSG: &str = "visudo - safely edit the sudoers file
+
+usage: visudo [-chqsV] [-f sudoers]
+
+Options:
+  -c, --check                   check-only mode
+  -f, --file=sudoers            specify sudoers file location
+  -h, --help                    display help message and exit
+  -q, --quiet                   less verbose (quiet) syntax error messages
+  -s, --strict                  strict syntax checking
+  -V, --version                 display version information and exit";
+
+/// The permissions of an installed sudoers file
+const SUDOERS_MODE: u32 = 0o440;
+
+fn main() {
+    let options = match VisudoOptions::from_env() {
+        Ok(options) => options,
+        Err(error) => {
+            eprintln!("visudo: {error}\n{USAGE_MSG}");
+            exit(1);
+        }
+    };
+
+    let path = match &options.file {
+        Some(file) => PathBuf::from(file),
+        None => match SudoConf::load() {
+            Ok(conf) => conf.sudoers_path(),
+            Err(error) => {
+                eprintln!("visudo: {SUDO_CONF_PATH}: {error}");
+                exit(1);
+            }
+        },
+    };
+    let path = path.as_path();
+
+    if options.help {
+        println!("{HELP_MSG}");
+    } else if options.version {
+        println!("visudo-rs {VERSION}");
+    } else if options.check {
+        if !check(path, &options) {
+            exit(1);
+        }
+    } else if let Err(error) = edit(path, &options) {
+        eprintln!("visudo: {error}");
+        exit(1);
+    }
+}
+
+/// Check the sudoers file at `path` (and the files it includes) without editing it
+fn check(path: &Path, options: &VisudoOptions) -> bool {
+    match report_problems(path, options) {
+        Ok(true) => {
+            if !options.quiet {
+                println!("{}: parsed OK", path.display());
+            }
+            true
+        }
+        Ok(false) => false,
+        Err(err) => {
+            if !options.quiet {
+                eprintln!(
+                    "visudo: cannot open sudoers file '{}': {err}",
+                    path.display()
+                );
+            }
+            false
+        }
+    }
+}
+
+/// Parse the sudoers file at `path` and report the problems in it (unless in quiet mode);
+/// returns whether the file is free of errors. Unused or undefined aliases are only errors in
+/// strict mode.
+fn report_problems(path: &Path, options: &VisudoOptions) -> io::Result<bool> {
+    let (sudoers, errors) = Sudoers::new(path)?;
+    let mut ok = errors.is_empty();
+
+    let report = |error: sudo::sudoers::Error, prefix: &str| {
+        if options.quiet {
+            return;
+        }
+        let sudo::sudoers::Error {
+            source,
+            location,
+            message,
+        } = error;
+        match location {
+            Some(range) => cited_error(
+                &format!("{prefix}{message}"),
+                range,
+                source.as_deref().unwrap_or(path),
+            ),
+            None => eprintln!("visudo: {prefix}{message}"),
+        }
+    };
+
+    for error in errors {
+        report(error, "");
+    }
+    for warning in sudoers.alias_warnings() {
+        if options.strict {
+            ok = false;
+            report(warning, "");
+        } else {
+            report(warning, "warning: ");
+        }
+    }
+
+    Ok(ok)
+}
+
+#[derive(Debug, PartialEq)]
+enum Choice {
+    EditAgain,
+    Exit,
+    SaveAnyway,
+}
+
+/// Lock the sudoers file at `path` and edit a copy of it until the user is satisfied
+fn edit(path: &Path, options: &VisudoOptions) -> io::Result<()> {
+    // if the sudoers file is a symbolic link, the file it points to should be replaced
+    let path = match fs::canonicalize(path) {
+        Ok(path) => path,
+        Err(err) if err.kind() == ErrorKind::NotFound => path.to_path_buf(),
+        Err(err) => return Err(err),
+    };
+
+    let sudoers_file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .mode(SUDOERS_MODE)
+        .open(&path)
+        .map_err(|err| file_error(&path, err))?;
+
+    sudoers_file.try_lock_exclusive().map_err(|err| {
+        if err.kind() == ErrorKind::WouldBlock {
+            io::Error::new(
+                ErrorKind::WouldBlock,
+                format!("{} busy, try again later", path.display()),
+            )
+        } else {
+            err
+        }
+    })?;
+
+    let original = fs::read(&path).map_err(|err| file_error(&path, err))?;
+    let editor = find_editor(&path)?;
+
+    // the copy lives next to the original, so that installing it is a single rename
+    let temp_path = PathBuf::from(format!("{}.tmp", path.display()));
+    let result = write_temp_file(&temp_path, &original)
+        .and_then(|()| edit_until_done(&editor, &path, &temp_path, &original, options))
+        .and_then(|save| {
+            if save {
+                install(&temp_path, &path)
+            } else {
+                Ok(())
+            }
+        });
+
+    // the lock on the old sudoers file is released when it is closed
+    let _ = fs::remove_file(&temp_path);
+
+    result
+}
+
+/// Determine the editor from the environment or the `editor` setting of the current sudoers
+fn find_editor(path: &Path) -> io::Result<Vec<String>> {
+    let sudoers = Sudoers::new(path)
+        .map(|(sudoers, _)| sudoers)
+        .unwrap_or_default();
+
+    let user = User::real()?.ok_or_else(|| {
+        io::Error::new(ErrorKind::NotFound, "could not determine the current user")
+    })?;
+    let host = hostname();
+
+    sudo::edit::resolve_editor(
+        &sudoers.editor(&user, &host),
+        sudoers.env_editor(&user, &host),
+    )
+    .ok_or_else(|| io::Error::new(ErrorKind::NotFound, "no usable editor found"))
+}
+
+fn write_temp_file(temp_path: &Path, contents: &[u8]) -> io::Result<()> {
+    // we hold the lock on the sudoers file, so any existing copy is a leftover of a crashed
+    // visudo and can be removed; 'create_new' then guarantees that no link is followed
+    match fs::remove_file(temp_path) {
+        Err(err) if err.kind() != ErrorKind::NotFound => return Err(file_error(temp_path, err)),
+        _ => {}
+    }
+
+    OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .custom_flags(libc::O_NOFOLLOW)
+        .mode(0o600)
+        .open(temp_path)
+        .and_then(|mut file| file.write_all(contents))
+        .map_err(|err| file_error(temp_path, err))
+}
+
+/// Run the editor on the temporary copy until it contains no errors or the user decides to
+/// stop; returns whether the copy should be installed
+fn edit_until_done(
+    editor: &[String],
+    path: &Path,
+    temp_path: &Path,
+    original: &[u8],
+    options: &VisudoOptions,
+) -> io::Result<bool> {
+    loop {
+        let status = Command::new(&editor[0])
+            .args(&editor[1..])
+            .arg(temp_path)
+            .status()
+            .map_err(|err| file_error(Path::new(&editor[0]), err))?;
+        if !status.success() {
+            return Err(io::Error::other(format!(
+                "{} exited with {status}, sudoers file unchanged",
+                editor[0]
+            )));
+        }
+
+        let contents = fs::read(temp_path).map_err(|err| file_error(temp_path, err))?;
+        if contents == original {
+            eprintln!("visudo: {} unchanged", path.display());
+            return Ok(false);
+        }
+
+        if report_problems(temp_path, options).map_err(|err| file_error(temp_path, err))? {
+            return Ok(true);
+        }
+
+        match ask_what_now()? {
+            Choice::EditAgain => continue,
+            Choice::Exit => return Ok(false),
+            Choice::SaveAnyway => return Ok(true),
+        }
+    }
+}
+
+fn ask_what_now() -> io::Result<Choice> {
+    let stdin = io::stdin();
+    loop {
+        print!("What now? ");
+        io::stdout().flush()?;
+
+        let mut answer = String::new();
+        if stdin.read_line(&mut answer)? == 0 {
+            // there is no one to answer the question, so be careful
+            println!();
+            return Ok(Choice::Exit);
+        }
+
+        match parse_choice(&answer) {
+            Some(choice) => return Ok(choice),
+            None => println!(
+                "Options are:
+  (e)dit sudoers file again
+  e(x)it without saving changes to sudoers file
+  (Q)uit and save changes to sudoers file (DANGER!)"
+            ),
+        }
+    }
+}
+
+fn parse_choice(answer: &str) -> Option<Choice> {
+    match answer.trim_start().chars().next()? {
+        'e' => Some(Choice::EditAgain),
+        'x' => Some(Choice::Exit),
+        'Q' => Some(Choice::SaveAnyway),
+        _ => None,
+    }
+}
+
+/// Atomically replace the sudoers file at `path` by `temp_path`, with the proper owner and mode
+fn install(temp_path: &Path, path: &Path) -> io::Result<()> {
+    let file = File::open(temp_path).map_err(|err| file_error(temp_path, err))?;
+    fchown(&file, 0, 0)
+        .and_then(|()| file.set_permissions(Permissions::from_mode(SUDOERS_MODE)))
+        .and_then(|()| file.sync_all())
+        .map_err(|err| file_error(temp_path, err))?;
+
+    fs::rename(temp_path, path).map_err(|err| file_error(path, err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_choice, Choice};
+
+    #[test]
+    fn parses_what_now_answers() {
+        assert_eq!(parse_choice("e\n"), Some(Choice::EditAgain));
+        assert_eq!(parse_choice("  x"), Some(Choice::Exit));
+        assert_eq!(parse_choice("Q\n"), Some(Choice::SaveAnyway));
+        // saving a broken file must be asked for explicitly
+        assert_eq!(parse_choice("q\n"), None);
+        assert_eq!(parse_choice("\n"), None);
+    }
+}